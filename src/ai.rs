@@ -1,49 +1,214 @@
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 use specs_derive::Component;
 
 use crate::components::*;
+use crate::room::RoomMap;
+use crate::visibility::Viewshed;
 
-#[derive(Component)]
+// how close the player has to get before an ambusher springs, as a distance
+pub const AMBUSH_RANGE: i32 = 3;
+// how far from its post a guard will go after the player
+pub const GUARD_RANGE: i32 = 4;
+
+// which ai an enemy gets, from enemies.json
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum AiData {
+    Chase,
+    Wander,
+    #[serde(alias = "stationary")]
+    Ambush,
+    Flee,
+    Guard,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct AiMoveToPlayer {}
 
-pub struct AiMoveToPlayerSystem {}
+// moves about at random, ie. bats
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct AiWander {}
+
+// sits still until the player comes close, then gives chase
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct AiAmbush {
+    pub range: i32,
+    pub sprung: bool,
+}
+
+// chases the player until it's been hurt badly enough, then runs away
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct AiFleeWhenHurt {}
+
+// stays by its post and only goes after the player when they get near it
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct AiGuard {
+    pub post_x: i32,
+    pub post_y: i32,
+    pub range: i32,
+}
 
-impl AiMoveToPlayerSystem {
-    fn find_nearest_player_position<'a>(
-        my_position: &Position,
-        players: &ReadStorage<'a, Player>,
-        positions: &ReadStorage<'a, Position>,
-    ) -> Option<Position> {
-        let mut found_position: Option<Position> = None;
-        let mut shortest_distance_sq_so_far = i32::MAX;
-
-        for (_player, position) in (players, positions).join() {
-            let distance = Position::distance_sq(my_position, position);
-            if distance < shortest_distance_sq_so_far {
-                shortest_distance_sq_so_far = distance;
-                found_position = Some(position.clone());
+// only players the enemy can actually see get noticed, anything without a viewshed knows where everyone is
+fn find_nearest_player_position<'a>(
+    my_position: &Position,
+    my_viewshed: Option<&Viewshed>,
+    players: &ReadStorage<'a, Player>,
+    positions: &ReadStorage<'a, Position>,
+) -> Option<Position> {
+    let mut found_position: Option<Position> = None;
+    let mut shortest_distance_sq_so_far = i32::MAX;
+
+    for (_player, position) in (players, positions).join() {
+        if let Some(viewshed) = my_viewshed {
+            if !viewshed.is_visible(position) {
+                continue;
             }
         }
-        found_position
+
+        let distance = Position::distance_sq(my_position, position);
+        if distance < shortest_distance_sq_so_far {
+            shortest_distance_sq_so_far = distance;
+            found_position = Some(position.clone());
+        }
     }
+    found_position
 }
 
+fn move_towards(room_map: &RoomMap, position: &Position, target_position: &Position, movement: &mut Movement) {
+    // no path means the target is out of reach, so just wait
+    if let Some(next_position) = room_map.next_step(position, target_position) {
+        let (move_x, move_y) = Position::delta(position, &next_position);
+        movement.add_movement_input(move_x, move_y);
+    }
+}
+
+pub struct AiMoveToPlayerSystem {}
+
 impl<'a> System<'a> for AiMoveToPlayerSystem {
     type SystemData = (
         ReadStorage<'a, AiMoveToPlayer>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, Player>,
+        ReadStorage<'a, ColliderComponent>,
+        ReadStorage<'a, Viewshed>,
+        WriteStorage<'a, Movement>,
+    );
+
+    fn run(&mut self, (ais, positions, players, colliders, viewsheds, mut movements): Self::SystemData) {
+        let room_map = RoomMap::from_colliders(&positions, &colliders, &movements);
+
+        for (_ai, position, viewshed, movement) in (&ais, &positions, viewsheds.maybe(), &mut movements).join() {
+            match find_nearest_player_position(position, viewshed, &players, &positions) {
+                Some(target_position) => move_towards(&room_map, position, &target_position, movement),
+                None => {}
+            }
+        }
+    }
+}
+
+pub struct AiWanderSystem {}
+
+impl<'a> System<'a> for AiWanderSystem {
+    type SystemData = (
+        ReadStorage<'a, AiWander>,
+        WriteExpect<'a, rltk::RandomNumberGenerator>,
+        WriteStorage<'a, Movement>,
+    );
+
+    fn run(&mut self, (ais, mut rng, mut movements): Self::SystemData) {
+        for (_ai, movement) in (&ais, &mut movements).join() {
+            movement.add_movement_input(rng.range(-1, 2), rng.range(-1, 2));
+        }
+    }
+}
+
+pub struct AiAmbushSystem {}
+
+impl<'a> System<'a> for AiAmbushSystem {
+    type SystemData = (
+        WriteStorage<'a, AiAmbush>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, ColliderComponent>,
+        ReadStorage<'a, Viewshed>,
+        WriteStorage<'a, Movement>,
+    );
+
+    fn run(&mut self, (mut ais, positions, players, colliders, viewsheds, mut movements): Self::SystemData) {
+        let room_map = RoomMap::from_colliders(&positions, &colliders, &movements);
+
+        for (ai, position, viewshed, movement) in (&mut ais, &positions, viewsheds.maybe(), &mut movements).join() {
+            if let Some(target_position) = find_nearest_player_position(position, viewshed, &players, &positions) {
+                if Position::distance_sq(position, &target_position) <= ai.range * ai.range {
+                    ai.sprung = true;
+                }
+
+                if ai.sprung {
+                    move_towards(&room_map, position, &target_position, movement);
+                }
+            }
+        }
+    }
+}
+
+pub struct AiFleeWhenHurtSystem {}
+
+impl<'a> System<'a> for AiFleeWhenHurtSystem {
+    type SystemData = (
+        ReadStorage<'a, AiFleeWhenHurt>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, ColliderComponent>,
+        ReadStorage<'a, Viewshed>,
         WriteStorage<'a, Movement>,
     );
 
-    fn run(&mut self, (ais, positions, players, mut movements): Self::SystemData) {
-        for (_ai, position, movement) in (&ais, &positions, &mut movements).join() {
-            match AiMoveToPlayerSystem::find_nearest_player_position(position, &players, &positions) {
-                Some(target_position) => {
-                    let (move_x, move_y) = Position::delta(position, &target_position);
+    fn run(&mut self, (ais, combat_stats, positions, players, colliders, viewsheds, mut movements): Self::SystemData) {
+        let room_map = RoomMap::from_colliders(&positions, &colliders, &movements);
+
+        for (_ai, combat_stat, position, viewshed, movement) in (&ais, &combat_stats, &positions, viewsheds.maybe(), &mut movements).join()
+        {
+            if let Some(target_position) = find_nearest_player_position(position, viewshed, &players, &positions) {
+                // half health or less and it's had enough
+                if combat_stat.health * 2 > combat_stat.max_health {
+                    move_towards(&room_map, position, &target_position, movement);
+                } else if let Some(next_position) = room_map.step_away(position, &target_position) {
+                    let (move_x, move_y) = Position::delta(position, &next_position);
                     movement.add_movement_input(move_x, move_y);
                 }
-                None => {}
+            }
+        }
+    }
+}
+
+pub struct AiGuardSystem {}
+
+impl<'a> System<'a> for AiGuardSystem {
+    type SystemData = (
+        ReadStorage<'a, AiGuard>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, ColliderComponent>,
+        ReadStorage<'a, Viewshed>,
+        WriteStorage<'a, Movement>,
+    );
+
+    fn run(&mut self, (ais, positions, players, colliders, viewsheds, mut movements): Self::SystemData) {
+        let room_map = RoomMap::from_colliders(&positions, &colliders, &movements);
+
+        for (ai, position, viewshed, movement) in (&ais, &positions, viewsheds.maybe(), &mut movements).join() {
+            let post = Position {
+                x: ai.post_x,
+                y: ai.post_y,
+            };
+            match find_nearest_player_position(&post, viewshed, &players, &positions) {
+                Some(target_position) if Position::distance_sq(&post, &target_position) <= ai.range * ai.range => {
+                    move_towards(&room_map, position, &target_position, movement);
+                }
+                _ if *position != post => move_towards(&room_map, position, &post, movement),
+                _ => {}
             }
         }
     }
@@ -1,11 +1,29 @@
 use crate::components::*;
+use crate::consumables::ActiveBuff;
+use crate::equipment::Equipment;
+use crate::items::ItemData;
+use crate::score::{Score, ScoreValue};
 use crate::StateAction;
 use specs::prelude::*;
 
+// a d20 plus the attacker's hit bonus has to reach this to land a blow
+const HIT_TARGET: i32 = 10;
+// a natural 20 always hits and does double damage, a natural 1 always misses
+const CRITICAL_ROLL: i32 = 20;
+const FUMBLE_ROLL: i32 = 1;
+
 pub struct DamageSystem {}
 
-fn kill(target: Entity, dead_tags: &mut WriteStorage<DeadTag>) {
-    dead_tags.insert(target, DeadTag {}).expect("failed to add kill tag!");
+fn kill(target: Entity, killer: Entity, cause: String, dead_tags: &mut WriteStorage<DeadTag>) {
+    dead_tags
+        .insert(
+            target,
+            DeadTag {
+                cause: cause,
+                killer: killer,
+            },
+        )
+        .expect("failed to add kill tag!");
 }
 
 fn add_damage(instigator: Entity, target: Entity, amount: i32, damages: &mut WriteStorage<ApplyDamageComponent>) {
@@ -21,6 +39,15 @@ fn add_damage(instigator: Entity, target: Entity, amount: i32, damages: &mut Wri
     }
 }
 
+// misses and crits are told from the player's side, whichever end of the attack they're on
+fn log_attack(attacker: Entity, target: Entity, attacker_text: String, target_text: String, combat_logs: &mut WriteStorage<CombatLog>) {
+    if let Some(combat_log) = combat_logs.get_mut(attacker) {
+        combat_log.push(attacker_text);
+    } else if let Some(combat_log) = combat_logs.get_mut(target) {
+        combat_log.push(target_text);
+    }
+}
+
 impl<'a> System<'a> for DamageSystem {
     type SystemData = (
         Entities<'a>,
@@ -32,6 +59,10 @@ impl<'a> System<'a> for DamageSystem {
         ReadStorage<'a, AppliesDamage>,
         WriteStorage<'a, CombatLog>,
         ReadStorage<'a, Description>,
+        WriteExpect<'a, rltk::RandomNumberGenerator>,
+        ReadStorage<'a, Equipment>,
+        ReadStorage<'a, ActiveBuff>,
+        ReadExpect<'a, Vec<ItemData>>,
     );
 
     fn run(
@@ -46,18 +77,80 @@ impl<'a> System<'a> for DamageSystem {
             applies_damages,
             mut combat_logs,
             descriptions,
+            mut rng,
+            equipments,
+            active_buffs,
+            item_datas,
         ): Self::SystemData,
     ) {
         // check for people that want to apply damage to an entity
         for (entity, applies_damage, wants_to_attack) in (&entities, &applies_damages, &mut wants_to_attack).join() {
-            add_damage(entity, wants_to_attack.target, applies_damage.damage, &mut apply_damages);
+            let target = wants_to_attack.target;
+            // walls and doors block the way but there's nothing there to fight
+            if !combat_stats.contains(target) {
+                continue;
+            }
+
+            let attacker_name = get_entity_name(entity, &descriptions).unwrap_or("it".to_string());
+            let target_name = get_entity_name(target, &descriptions).unwrap_or("it".to_string());
+
+            let hit_roll = rng.roll_dice(1, 20);
+            if hit_roll == FUMBLE_ROLL || (hit_roll != CRITICAL_ROLL && hit_roll + applies_damage.hit_bonus < HIT_TARGET) {
+                log_attack(
+                    entity,
+                    target,
+                    format!("You missed the {}", target_name),
+                    format!("{} missed you", attacker_name),
+                    &mut combat_logs,
+                );
+                continue;
+            }
+
+            // base damage plus whatever the attacker is wielding, less whatever the target is wearing, buffs on top
+            let attack_bonus = equipments.get(entity).map_or(0, |equipment| equipment.attack_bonus(&item_datas))
+                + active_buffs.get(entity).map_or(0, |active_buff| active_buff.attack_bonus);
+            let defense = equipments.get(target).map_or(0, |equipment| equipment.defense(&item_datas))
+                + active_buffs.get(target).map_or(0, |active_buff| active_buff.defense);
+            let mut amount = rng.roll(applies_damage.damage) + attack_bonus;
+            if hit_roll == CRITICAL_ROLL {
+                amount *= 2;
+                log_attack(
+                    entity,
+                    target,
+                    format!("Critical hit on the {}!", target_name),
+                    format!("Critical hit from {}!", attacker_name),
+                    &mut combat_logs,
+                );
+            }
+
+            amount -= defense;
+            if amount <= 0 {
+                log_attack(
+                    entity,
+                    target,
+                    format!("The {} shrugged it off", target_name),
+                    format!("{} couldn't get through", attacker_name),
+                    &mut combat_logs,
+                );
+                continue;
+            }
+            add_damage(entity, target, amount, &mut apply_damages);
         }
         wants_to_attack.clear();
 
         // apply damages
-        for (entity, combat_stat, apply_damages) in (&entities, &mut combat_stats, &apply_damages).join() {
+        for (entity, combat_stat, apply_damage) in (&entities, &mut combat_stats, &apply_damages).join() {
+            // anything without health can't be hurt, it has to be dealt with some other way
+            if combat_stat.max_health <= 0 {
+                if let Some(combat_log) = combat_logs.get_mut(apply_damage.instigator) {
+                    let name = get_entity_name(entity, &descriptions).unwrap_or("it".to_string());
+                    combat_log.push(format!("{} can't be hurt!", name));
+                }
+                continue;
+            }
+
             if combat_stat.health > 0 {
-                combat_stat.health -= apply_damages.amounts.iter().sum::<i32>();
+                combat_stat.health -= apply_damage.amounts.iter().sum::<i32>();
                 if let Some(combat_log) = combat_logs.get_mut(entity) {
                     combat_log.push(format!("You were hit"));
                 } else {
@@ -74,7 +167,11 @@ impl<'a> System<'a> for DamageSystem {
                 }
 
                 if combat_stat.health <= 0 {
-                    kill(entity, &mut dead_tags);
+                    let cause = match get_entity_name(apply_damage.instigator, &descriptions) {
+                        Some(name) => format!("killed by {}", name),
+                        None => "killed".to_string(),
+                    };
+                    kill(entity, apply_damage.instigator, cause, &mut dead_tags);
                     println!("killed!");
                 }
             }
@@ -102,8 +199,15 @@ pub fn attack(attacker: Entity, target: Entity, attacks: &mut WriteStorage<Wants
 
 pub struct MeleeCombatSystem {}
 
-fn find_target_at(target_x: i32, target_y: i32, entities: &Entities, positions: &ReadStorage<Position>) -> Option<Entity> {
-    for (entity, position) in (entities, positions).join() {
+// only something with combat stats can be fought, anything else just blocked the move
+fn find_target_at(
+    target_x: i32,
+    target_y: i32,
+    entities: &Entities,
+    positions: &ReadStorage<Position>,
+    combat_stats: &ReadStorage<CombatStats>,
+) -> Option<Entity> {
+    for (entity, position, _combat_stats) in (entities, positions, combat_stats).join() {
         if position.equals_xy(target_x, target_y) {
             return Some(entity);
         }
@@ -118,14 +222,15 @@ impl<'a> System<'a> for MeleeCombatSystem {
         ReadStorage<'a, Position>,
         ReadStorage<'a, AppliesDamage>,
         WriteStorage<'a, WantsToAttack>,
+        ReadStorage<'a, CombatStats>,
     );
 
-    fn run(&mut self, (entities, movements, positions, damage_stats, mut wants_to_attack): Self::SystemData) {
+    fn run(&mut self, (entities, movements, positions, damage_stats, mut wants_to_attack, combat_stats): Self::SystemData) {
         for (entity, movement, position, _damage_stats) in (&entities, &movements, &positions, &damage_stats).join() {
             if movement.was_move_blocked() {
                 let (delta_x, delta_y) = movement.get_attempted_move();
                 let (target_x, target_y) = (position.x + delta_x, position.y + delta_y);
-                match find_target_at(target_x, target_y, &entities, &positions) {
+                match find_target_at(target_x, target_y, &entities, &positions, &combat_stats) {
                     Some(target) => {
                         attack(entity, target, &mut wants_to_attack);
                     }
@@ -145,13 +250,32 @@ impl ClearDeadSystem {
 }
 
 impl<'a> System<'a> for ClearDeadSystem {
-    type SystemData = (Entities<'a>, ReadStorage<'a, DeadTag>, WriteExpect<'a, Vec<StateAction>>);
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, DeadTag>,
+        ReadStorage<'a, ScoreValue>,
+        WriteExpect<'a, Score>,
+        WriteExpect<'a, Vec<StateAction>>,
+    );
 
-    fn run(&mut self, (entities, dead_tags, mut state_actions): Self::SystemData) {
+    fn run(&mut self, (entities, players, dead_tags, score_values, mut score, mut state_actions): Self::SystemData) {
         let mut entities_to_delete = Vec::new();
 
-        for (entity, _dead_tag) in (&entities, &dead_tags).join() {
-            entities_to_delete.push(entity);
+        for (entity, dead_tag) in (&entities, &dead_tags).join() {
+            // the player sticks around so the game over screen still has something to show
+            if players.contains(entity) {
+                state_actions.push(StateAction::PlayerDied {
+                    cause: dead_tag.cause.clone(),
+                });
+            } else {
+                if let Some(score_value) = score_values.get(entity) {
+                    if players.contains(dead_tag.killer) {
+                        score.kills += score_value.points;
+                    }
+                }
+                entities_to_delete.push(entity);
+            }
         }
 
         state_actions.push(StateAction::DeleteEntities {
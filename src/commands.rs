@@ -0,0 +1,228 @@
+use specs::prelude::*;
+use std::sync::Arc;
+
+use crate::components::{ActiveDescriptionComponent, DebugHudComponent, Description, InventoryComponent, Player, PlayerTextInputComponent};
+use crate::items::ItemData;
+use crate::textinput::{did_you_mean, fuzzy_match, FuzzyMatch, ParsedCommand, TextParser};
+
+// what the player typed, after the parser has been over it
+pub struct CommandArgs {
+    pub player: Entity,
+    pub verb: String,           // the name or alias that was typed, ie. "wave" for the use command
+    pub arg: Option<String>,    // the thing being acted on
+    pub target: Option<String>, // what it's being done to
+}
+
+// something the player can type, each gameplay module registers its own
+pub trait TextCommand: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // other verbs handled by the same command, ie. wear for wield
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn help(&self) -> &'static str;
+
+    // acts on something in the room or the inventory, so its objects get checked for typos too
+    fn takes_object(&self) -> bool {
+        false
+    }
+
+    // anything that can't be undone has to be typed properly, close enough only gets a suggestion
+    fn needs_exact_name(&self) -> bool {
+        false
+    }
+
+    // only there with the debug hud on, and never guessed at
+    fn debug_only(&self) -> bool {
+        false
+    }
+
+    // None means the command didn't understand its args
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String>;
+}
+
+pub struct CommandRegistry {
+    commands: Vec<Arc<dyn TextCommand>>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self { commands: Vec::new() }
+    }
+
+    pub fn register(&mut self, command: impl TextCommand + 'static) {
+        self.commands.push(Arc::new(command));
+    }
+
+    pub fn find(&self, verb: &str, debug: bool) -> Option<Arc<dyn TextCommand>> {
+        self.commands
+            .iter()
+            .filter(|command| debug || !command.debug_only())
+            .find(|command| command.name() == verb || command.aliases().contains(&verb))
+            .cloned()
+    }
+
+    // every verb that can be guessed at from a typo
+    fn verbs(&self) -> Vec<&'static str> {
+        let mut verbs = Vec::new();
+        for command in self.commands.iter().filter(|command| !command.debug_only()) {
+            verbs.push(command.name());
+            verbs.extend_from_slice(command.aliases());
+        }
+
+        // one letter is too short to guess at, everything is one typo away from it
+        verbs.retain(|verb| verb.len() > 1);
+        verbs
+    }
+}
+
+struct HelpCommand {}
+
+impl TextCommand for HelpCommand {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn help(&self) -> &'static str {
+        "help <command> to find out more about it"
+    }
+
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String> {
+        let debug = world.read_storage::<DebugHudComponent>().contains(args.player);
+        let registry = world.fetch::<CommandRegistry>();
+
+        if let Some(verb) = &args.arg {
+            return match registry.find(verb.as_str(), debug) {
+                Some(command) if command.aliases().is_empty() => Some(command.help().to_string()),
+                Some(command) => Some(format!("{}, also {}", command.help(), command.aliases().join(", "))),
+                None => Some(format!("there is no {} command", verb)),
+            };
+        }
+
+        let names: Vec<&str> = registry
+            .commands
+            .iter()
+            .filter(|command| debug || !command.debug_only())
+            .map(|command| command.name())
+            .collect();
+        Some(format!("commands: {}", names.join(", ")))
+    }
+}
+
+pub fn register_commands(registry: &mut CommandRegistry) {
+    registry.register(HelpCommand {});
+}
+
+// runs whatever the players typed, the result is shown in the hud
+pub fn process_text_commands(world: &mut World) {
+    let mut submitted = Vec::new();
+    for (entity, _player, text_input) in (
+        &world.entities(),
+        &world.read_storage::<Player>(),
+        &mut world.write_storage::<PlayerTextInputComponent>(),
+    )
+        .join()
+    {
+        if let Some(text_command) = text_input.consume() {
+            submitted.push((entity, text_command));
+        }
+    }
+
+    for (entity, text_command) in submitted {
+        let result = process_text_input(world, entity, &text_command);
+        if let Some(description) = world.write_storage::<ActiveDescriptionComponent>().get_mut(entity) {
+            match result {
+                Some(result) => description.set(result.as_str()),
+                None => description.set("i don't understand"),
+            }
+        }
+    }
+}
+
+fn process_text_input(world: &mut World, player: Entity, text_command: &String) -> Option<String> {
+    let (verb, arg, target) = match world.fetch::<TextParser>().parse(text_command) {
+        ParsedCommand::Some { command, arg, target } => (command, arg, target),
+        ParsedCommand::None => return None,
+    };
+
+    // typos get corrected when there's only one thing they could be, otherwise the player gets asked
+    let debug = world.read_storage::<DebugHudComponent>().contains(player);
+    let (command, verb) = {
+        let registry = world.fetch::<CommandRegistry>();
+        match registry.find(verb.as_str(), debug) {
+            Some(command) => (command, verb),
+            None => match fuzzy_match(verb.as_str(), registry.verbs()) {
+                FuzzyMatch::Found(guess) => {
+                    let command = registry.find(guess.as_str(), debug)?;
+                    if command.needs_exact_name() {
+                        return Some(did_you_mean(&[guess]));
+                    }
+                    (command, guess)
+                }
+                FuzzyMatch::Suggestions(guesses) => return Some(did_you_mean(&guesses)),
+                FuzzyMatch::NotFound => return None,
+            },
+        }
+    };
+
+    let object_names = if command.takes_object() {
+        get_object_names(world, player)
+    } else {
+        Vec::new()
+    };
+    let (arg, target) = match (resolve_object_name(arg, &object_names), resolve_object_name(target, &object_names)) {
+        (Ok(arg), Ok(target)) => (arg, target),
+        (Err(suggestion), _) | (_, Err(suggestion)) => return Some(suggestion),
+    };
+
+    // "it" is whatever was last acted on, going by the corrected name
+    if let Some(object) = arg.as_ref().filter(|_object| command.takes_object()) {
+        world.write_resource::<TextParser>().remember_object(object.as_str());
+    }
+
+    let args = CommandArgs {
+        player: player,
+        verb: verb,
+        arg: arg,
+        target: target,
+    };
+    command.execute(world, &args)
+}
+
+// everything the player could be talking about, whatever's in the room and whatever they're carrying
+fn get_object_names(world: &World, player: Entity) -> Vec<String> {
+    let mut object_names = Vec::new();
+    for description in world.read_storage::<Description>().join() {
+        object_names.push(description.input_name.clone());
+        object_names.push(description.name.to_ascii_lowercase());
+    }
+
+    let item_datas = world.fetch::<Vec<ItemData>>();
+    if let Some(inventory) = world.read_storage::<InventoryComponent>().get(player) {
+        for item in inventory.items().each() {
+            if let Some(item_data) = crate::items::find_item(item, &item_datas) {
+                if let Some(input_name) = &item_data.input_name {
+                    object_names.push(input_name.clone());
+                }
+                object_names.push(item_data.name.to_ascii_lowercase());
+            }
+        }
+    }
+    object_names
+}
+
+// names that aren't close to anything are left alone, the command gets to say it isn't there
+fn resolve_object_name(maybe_name: Option<String>, object_names: &[String]) -> Result<Option<String>, String> {
+    let name = match maybe_name {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+
+    match fuzzy_match(name.as_str(), object_names.iter().map(|object_name| object_name.as_str())) {
+        FuzzyMatch::Found(object_name) => Ok(Some(object_name)),
+        FuzzyMatch::Suggestions(suggestions) => Err(did_you_mean(&suggestions)),
+        FuzzyMatch::NotFound => Ok(Some(name)),
+    }
+}
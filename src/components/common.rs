@@ -23,10 +23,10 @@ impl Position {
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct ColliderComponent {}
 
-#[derive(Component, ConvertSaveload)]
+#[derive(Component, ConvertSaveload, Clone)]
 pub struct DebugName {
     pub text: String,
 }
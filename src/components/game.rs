@@ -1,20 +1,22 @@
 use crate::items::ItemFlags;
-use rltk::VirtualKeyCode;
+use rltk::{DiceType, VirtualKeyCode};
 use serde::{Deserialize, Serialize};
 use specs::error::NoError;
 use specs::prelude::*;
 use specs::saveload::{ConvertSaveload, Marker};
 use specs_derive::{Component, ConvertSaveload};
 
-#[derive(Component, Serialize, Deserialize)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct CombatStats {
     pub health: i32,
     pub max_health: i32,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct AppliesDamage {
-    pub damage: i32,
+    pub damage: DiceType, // rolled on every hit, ie. 1d4
+    #[serde(default)]
+    pub hit_bonus: i32, // added to the d20 to hit roll
 }
 
 #[derive(Component)]
@@ -29,9 +31,12 @@ pub struct WantsToAttack {
 }
 
 #[derive(Component)]
-pub struct DeadTag {}
+pub struct DeadTag {
+    pub cause: String,
+    pub killer: Entity,
+}
 
-#[derive(Component, Serialize, Deserialize)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct CombatLog {
     pub logs: Vec<String>,
 }
@@ -49,7 +54,7 @@ impl CombatLog {
     }
 }
 
-#[derive(Component, Debug, Serialize, Deserialize)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct PlayerInputComponent {
     pub move_left: bool,
     pub move_right: bool,
@@ -75,7 +80,7 @@ impl PlayerInputComponent {
     }
 }
 
-#[derive(Component, Debug, Serialize, Deserialize)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct PlayerInputMappingComponent {}
 
 impl PlayerInputMappingComponent {
@@ -92,7 +97,7 @@ impl PlayerInputMappingComponent {
     }
 }
 
-#[derive(Component, Debug, Serialize, Deserialize)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct PlayerTextInputComponent {
     input_text: String,
     submitted: bool,
@@ -156,7 +161,12 @@ pub struct PickupTrigger {
     pub item_to_pickup: ItemFlags,
 }
 
-#[derive(Component, ConvertSaveload)]
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct CarryCapacity {
+    pub max_weight: i32,
+}
+
+#[derive(Component, ConvertSaveload, Clone)]
 pub struct InventoryComponent {
     items: ItemFlags,
 }
@@ -168,7 +178,13 @@ impl InventoryComponent {
     pub fn add(&mut self, item: ItemFlags) {
         self.items |= item;
     }
+    pub fn remove(&mut self, item: ItemFlags) {
+        self.items &= !item;
+    }
     pub fn has(&self, item: ItemFlags) -> bool {
         (self.items & item) == item
     }
+    pub fn items(&self) -> ItemFlags {
+        self.items
+    }
 }
@@ -6,6 +6,8 @@ use specs_derive::{Component, ConvertSaveload};
 use std::cmp::*;
 use std::str::FromStr;
 
+use crate::items::ItemFlags;
+
 mod common;
 mod game;
 
@@ -37,10 +39,38 @@ impl Description {
     }
 }
 
+// something the player has to satisfy, checked against what they're carrying
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Condition {
+    pub requires: ItemFlags,
+}
+
+impl Condition {
+    pub fn is_met(&self, inventory: &InventoryComponent) -> bool {
+        inventory.has(self.requires)
+    }
+}
+
+// replaces the regular description while its condition is met, ie. the book can only be read with glasses
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct ConditionalDescription {
+    pub condition: Condition,
+    pub description: String,
+}
+
+impl ConditionalDescription {
+    pub fn get_description(&self, inventory: &InventoryComponent) -> Option<&str> {
+        if self.condition.is_met(inventory) {
+            return Some(self.description.as_str());
+        }
+        None
+    }
+}
+
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Player {}
 
-#[derive(Component, Serialize, Deserialize)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Movement {
     cumulative_x_movement: i32,
     cumulative_y_movement: i32,
@@ -105,7 +135,7 @@ impl Movement {
     }
 }
 
-#[derive(Component, Serialize, Deserialize)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct ActiveDescriptionComponent {
     pub description: String,
 }
@@ -122,5 +152,8 @@ impl ActiveDescriptionComponent {
     }
 }
 
-#[derive(Component, Serialize, Deserialize)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct DebugHudComponent {}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct InventoryHudComponent {}
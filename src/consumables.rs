@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use specs_derive::Component;
+
+use crate::commands::{CommandArgs, CommandRegistry, TextCommand};
+use crate::components::{CombatLog, CombatStats, InventoryComponent, Player};
+use crate::items::{find_item, find_item_by_name, ItemData, ItemFlags};
+use crate::StateAction;
+
+// what happens when an item gets used up, from items.json
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ConsumableData {
+    pub verb: String, // drink, eat
+    #[serde(default)]
+    pub heal: i32,
+    pub buff: Option<BuffData>,
+    pub description: String, // shown once it's gone
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BuffData {
+    #[serde(default)]
+    pub attack_bonus: i32,
+    #[serde(default)]
+    pub defense: i32,
+    pub turns: i32,
+}
+
+// a boost that lasts for a number of player turns, consuming something else replaces it
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct ActiveBuff {
+    pub attack_bonus: i32,
+    pub defense: i32,
+    pub turns_left: i32,
+}
+
+// heals the player and sets up any buff, the item is gone afterwards
+pub fn consume_item(world: &mut World, item: ItemFlags) {
+    let consumable = match find_item(item, &world.fetch::<Vec<ItemData>>()).and_then(|item_data| item_data.consumable.clone()) {
+        Some(consumable) => consumable,
+        None => return,
+    };
+
+    let mut combat_stats = world.write_storage::<CombatStats>();
+    let mut active_buffs = world.write_storage::<ActiveBuff>();
+    for (entity, _player, inventory) in (
+        &world.entities(),
+        &world.read_storage::<Player>(),
+        &mut world.write_storage::<InventoryComponent>(),
+    )
+        .join()
+    {
+        if !inventory.has(item) {
+            continue;
+        }
+        inventory.remove(item);
+
+        if let Some(combat_stat) = combat_stats.get_mut(entity) {
+            combat_stat.health = i32::min(combat_stat.health + consumable.heal, combat_stat.max_health);
+        }
+
+        if let Some(buff) = &consumable.buff {
+            let active_buff = ActiveBuff {
+                attack_bonus: buff.attack_bonus,
+                defense: buff.defense,
+                turns_left: buff.turns,
+            };
+            active_buffs.insert(entity, active_buff).expect("failed to add buff");
+        }
+    }
+}
+
+// counts down the buffs, only runs on turns where the player moved
+pub struct BuffSystem {}
+
+impl<'a> System<'a> for BuffSystem {
+    type SystemData = (Entities<'a>, WriteStorage<'a, ActiveBuff>, WriteStorage<'a, CombatLog>);
+
+    fn run(&mut self, (entities, mut active_buffs, mut combat_logs): Self::SystemData) {
+        let mut worn_off = Vec::new();
+        for (entity, active_buff) in (&entities, &mut active_buffs).join() {
+            active_buff.turns_left -= 1;
+            if active_buff.turns_left <= 0 {
+                worn_off.push(entity);
+            }
+        }
+
+        for entity in worn_off {
+            active_buffs.remove(entity);
+            if let Some(combat_log) = combat_logs.get_mut(entity) {
+                combat_log.push("The effects wear off".to_string());
+            }
+        }
+    }
+}
+
+pub fn register_commands(registry: &mut CommandRegistry) {
+    registry.register(DrinkCommand {});
+}
+
+struct DrinkCommand {}
+
+impl TextCommand for DrinkCommand {
+    fn name(&self) -> &'static str {
+        "drink"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["eat"]
+    }
+
+    fn help(&self) -> &'static str {
+        "drink or eat something you are carrying"
+    }
+
+    fn takes_object(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String> {
+        let item_name = match &args.arg {
+            Some(item_name) => item_name,
+            None => return Some(format!("{} what?", args.verb)),
+        };
+
+        let inventories = world.read_storage::<InventoryComponent>();
+        Some(consume(
+            inventories.get(args.player)?,
+            &world.fetch::<Vec<ItemData>>(),
+            args.verb.as_str(),
+            item_name.as_str(),
+            &mut world.write_resource::<Vec<StateAction>>(),
+        ))
+    }
+}
+
+// "use" works for anything that can be consumed, otherwise the verb has to fit, ie. drink the wine
+pub fn consume(
+    inventory: &InventoryComponent,
+    item_datas: &Vec<ItemData>,
+    consume_command: &str,
+    item_name: &str,
+    state_actions: &mut Vec<StateAction>,
+) -> String {
+    let item_data = match find_item_by_name(item_name, item_datas) {
+        Some(item_data) if inventory.has(item_data.flag) => item_data,
+        _ => return format!("you don't have a {}", item_name),
+    };
+
+    match &item_data.consumable {
+        Some(consumable) if consume_command == "use" || consumable.verb == consume_command => {
+            state_actions.push(StateAction::ConsumeItem { item: item_data.flag });
+            consumable.description.clone()
+        }
+        _ => format!("you can't {} the {}", consume_command, item_data.name.to_ascii_lowercase()),
+    }
+}
@@ -0,0 +1,98 @@
+use specs::prelude::*;
+
+use crate::commands::{CommandArgs, CommandRegistry, TextCommand};
+use crate::room::ExitDirection;
+use crate::StateAction;
+
+// only available with the debug hud on
+pub fn register_commands(registry: &mut CommandRegistry) {
+    registry.register(DebugSaveCommand {});
+    registry.register(DebugLoadCommand {});
+    registry.register(DebugRedirectCommand {});
+}
+
+// "go <room number>" with the debug hud on, the go command hands it over
+pub fn go_to_room(world: &mut World, to_room: i32) -> String {
+    world.write_resource::<Vec<StateAction>>().push(StateAction::ChangeRoom {
+        direction: ExitDirection::Invalid,
+        to_room: to_room,
+    });
+    format!("going to room {}", to_room)
+}
+
+struct DebugSaveCommand {}
+
+impl TextCommand for DebugSaveCommand {
+    fn name(&self) -> &'static str {
+        "dsave"
+    }
+
+    fn help(&self) -> &'static str {
+        "save the world as it is, for debugging"
+    }
+
+    fn debug_only(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, world: &mut World, _args: &CommandArgs) -> Option<String> {
+        world.write_resource::<Vec<StateAction>>().push(StateAction::DebugSave);
+        Some("debug saved".to_string())
+    }
+}
+
+struct DebugLoadCommand {}
+
+impl TextCommand for DebugLoadCommand {
+    fn name(&self) -> &'static str {
+        "dload"
+    }
+
+    fn help(&self) -> &'static str {
+        "load the last debug save"
+    }
+
+    fn debug_only(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, world: &mut World, _args: &CommandArgs) -> Option<String> {
+        world.write_resource::<Vec<StateAction>>().push(StateAction::DebugLoad);
+        Some("debug loaded".to_string())
+    }
+}
+
+struct DebugRedirectCommand {}
+
+impl TextCommand for DebugRedirectCommand {
+    fn name(&self) -> &'static str {
+        "redirect"
+    }
+
+    fn help(&self) -> &'static str {
+        "redirect <room> <new room> sends every exit into a room somewhere else"
+    }
+
+    fn debug_only(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String> {
+        // the parser keeps "12 34" together as one object
+        let rooms: Vec<i32> = args
+            .arg
+            .as_ref()?
+            .split_whitespace()
+            .filter_map(|room_text| room_text.parse::<i32>().ok())
+            .collect();
+        if rooms.len() != 2 {
+            return None;
+        }
+
+        world.write_resource::<Vec<StateAction>>().push(StateAction::RedirectRoom {
+            original_room: rooms[0],
+            new_room: rooms[1],
+        });
+        Some(format!("room {} now goes to {}", rooms[0], rooms[1]))
+    }
+}
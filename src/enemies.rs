@@ -5,10 +5,13 @@ use std::fs::File;
 
 use crate::components::*;
 
-use crate::ai::AiMoveToPlayer;
+use crate::ai::*;
 use crate::components::{AppliesDamage, CombatStats};
 use crate::game::DynamicMarker;
+use crate::items::{ConditionalDescriptionData, ItemData};
 use crate::render::Renderable;
+use crate::score::ScoreValue;
+use crate::visibility::{Viewshed, ENEMY_VIEW_RANGE};
 
 pub fn create_enemy(world: &mut World, room: i32, enemy_name: &str, x: i32, y: i32, health: Option<i32>) {
     let enemy = find_enemy_by_name(enemy_name, &world.fetch::<Vec<EnemyData>>())
@@ -18,6 +21,11 @@ pub fn create_enemy(world: &mut World, room: i32, enemy_name: &str, x: i32, y: i
 }
 
 fn spawn_enemy(world: &mut World, room: i32, item: &EnemyData, x: i32, y: i32, health: Option<i32>) {
+    let conditional_description = item
+        .conditional
+        .as_ref()
+        .map(|conditional| conditional.to_component(&world.fetch::<Vec<ItemData>>()));
+
     let mut entity = world
         .create_entity()
         .with(Position { x: x, y: y })
@@ -41,8 +49,44 @@ fn spawn_enemy(world: &mut World, room: i32, item: &EnemyData, x: i32, y: i32, h
         })
         .marked::<SimpleMarker<DynamicMarker>>();
 
-    if let Some(damage) = item.damage {
-        entity = entity.with(AppliesDamage { damage: damage }).with(AiMoveToPlayer {})
+    if let Some(damage) = &item.damage {
+        entity = entity.with(AppliesDamage {
+            damage: rltk::parse_dice_string(damage).expect("invalid enemy damage dice"),
+            hit_bonus: item.hit_bonus.unwrap_or(0),
+        });
+    }
+
+    let ai = match item.damage {
+        Some(_) => Some(item.ai.unwrap_or(AiData::Chase)),
+        None => item.ai,
+    };
+    entity = match ai {
+        Some(AiData::Chase) => entity.with(AiMoveToPlayer {}),
+        Some(AiData::Wander) => entity.with(AiWander {}),
+        Some(AiData::Ambush) => entity.with(AiAmbush {
+            range: AMBUSH_RANGE,
+            sprung: false,
+        }),
+        Some(AiData::Flee) => entity.with(AiFleeWhenHurt {}),
+        Some(AiData::Guard) => entity.with(AiGuard {
+            post_x: x,
+            post_y: y,
+            range: GUARD_RANGE,
+        }),
+        None => entity,
+    };
+
+    // anything with a mind of its own has to see the player before it reacts
+    if ai.is_some() {
+        entity = entity.with(Viewshed::new(ENEMY_VIEW_RANGE));
+    }
+
+    if let Some(points) = item.points {
+        entity = entity.with(ScoreValue { points: points });
+    }
+
+    if let Some(conditional_description) = conditional_description {
+        entity = entity.with(conditional_description);
     }
 
     entity.build();
@@ -68,8 +112,12 @@ struct EnemyData {
     pub input_name: Option<String>,
     pub description: String,
     pub glyph: char,
-    pub health: i32, // max health
-    pub damage: Option<i32>,
+    pub health: i32,            // max health
+    pub damage: Option<String>, // dice, ie. 1d4
+    pub hit_bonus: Option<i32>,
+    pub points: Option<i32>,
+    pub conditional: Option<ConditionalDescriptionData>,
+    pub ai: Option<AiData>, // anything that does damage chases by default
 }
 
 pub fn load_enemies(world: &mut World) {
@@ -91,7 +139,11 @@ fn save_enemies() {
         description: "It's smelly!".to_string(),
         glyph: '\u{263A}',
         health: 1,
-        damage: Some(1),
+        damage: Some("1d4".to_string()),
+        hit_bonus: Some(0),
+        points: Some(25),
+        conditional: None,
+        ai: None,
     };
     enemies.push(e);
 
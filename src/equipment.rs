@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use specs_derive::Component;
+
+use crate::commands::{CommandArgs, CommandRegistry, TextCommand};
+use crate::components::{InventoryComponent, Player};
+use crate::items::{find_item, find_item_by_name, ItemData, ItemFlags};
+use crate::StateAction;
+
+// where an item goes when it's put on, only one item fits in each
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum EquipmentSlot {
+    Weapon,
+    Head,
+}
+
+// what an item does while it's wielded or worn, from items.json
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EquipmentData {
+    pub slot: EquipmentSlot,
+    #[serde(default)]
+    pub attack_bonus: i32, // added to every hit
+    #[serde(default)]
+    pub defense: i32, // taken off every hit against the wearer
+}
+
+// which of the carried items are in use
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Equipment {
+    items: ItemFlags,
+}
+
+impl Equipment {
+    pub fn new() -> Self {
+        Self { items: ItemFlags::EMPTY }
+    }
+
+    pub fn has(&self, item: ItemFlags) -> bool {
+        !item.is_empty() && (self.items & item) == item
+    }
+
+    pub fn attack_bonus(&self, item_datas: &Vec<ItemData>) -> i32 {
+        self.equipment_datas(item_datas)
+            .iter()
+            .map(|equipment| equipment.attack_bonus)
+            .sum()
+    }
+
+    pub fn defense(&self, item_datas: &Vec<ItemData>) -> i32 {
+        self.equipment_datas(item_datas).iter().map(|equipment| equipment.defense).sum()
+    }
+
+    fn equipment_datas<'a>(&self, item_datas: &'a Vec<ItemData>) -> Vec<&'a EquipmentData> {
+        self.items
+            .each()
+            .iter()
+            .filter_map(|item| find_item(*item, item_datas))
+            .filter_map(|item_data| item_data.equipment.as_ref())
+            .collect()
+    }
+}
+
+// puts on a carried item, swapping out whatever was already in its slot
+pub fn equip_item(world: &mut World, item: ItemFlags) {
+    let item_datas = world.fetch::<Vec<ItemData>>();
+    let slot = match find_item(item, &item_datas).and_then(|item_data| item_data.equipment.as_ref()) {
+        Some(equipment_data) => equipment_data.slot,
+        None => return,
+    };
+
+    for (_player, inventory, equipment) in (
+        &world.read_storage::<Player>(),
+        &world.read_storage::<InventoryComponent>(),
+        &mut world.write_storage::<Equipment>(),
+    )
+        .join()
+    {
+        if !inventory.has(item) {
+            continue;
+        }
+
+        for equipped_item in equipment.items.each() {
+            let same_slot = find_item(equipped_item, &item_datas)
+                .and_then(|item_data| item_data.equipment.as_ref())
+                .is_some_and(|equipment_data| equipment_data.slot == slot);
+            if same_slot {
+                equipment.items &= !equipped_item;
+            }
+        }
+        equipment.items |= item;
+    }
+}
+
+pub fn unequip_item(world: &mut World, item: ItemFlags) {
+    for (_player, equipment) in (&world.read_storage::<Player>(), &mut world.write_storage::<Equipment>()).join() {
+        equipment.items &= !item;
+    }
+}
+
+pub fn register_commands(registry: &mut CommandRegistry) {
+    registry.register(WieldCommand {});
+    registry.register(RemoveCommand {});
+}
+
+struct WieldCommand {}
+
+impl TextCommand for WieldCommand {
+    fn name(&self) -> &'static str {
+        "wield"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["wear"]
+    }
+
+    fn help(&self) -> &'static str {
+        "wield a weapon or wear armour you are carrying"
+    }
+
+    fn takes_object(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String> {
+        let item_name = match &args.arg {
+            Some(item_name) => item_name,
+            None => return Some(format!("{} what?", args.verb)),
+        };
+
+        let inventories = world.read_storage::<InventoryComponent>();
+        let equipments = world.read_storage::<Equipment>();
+        let item_datas = world.fetch::<Vec<ItemData>>();
+        let item_data = match find_item_by_name(item_name.as_str(), &item_datas) {
+            Some(item_data) if inventories.get(args.player).is_some_and(|inventory| inventory.has(item_data.flag)) => item_data,
+            _ => return Some(format!("you don't have a {}", item_name)),
+        };
+
+        // weapons get wielded, everything else gets worn
+        let display_name = item_data.name.to_ascii_lowercase();
+        match &item_data.equipment {
+            Some(equipment_data) if (equipment_data.slot == EquipmentSlot::Weapon) == (args.verb == "wield") => {}
+            _ => return Some(format!("you can't {} the {}", args.verb, display_name)),
+        }
+
+        if equipments.get(args.player).is_some_and(|equipment| equipment.has(item_data.flag)) {
+            return Some(format!("you already have the {} on", display_name));
+        }
+
+        world
+            .write_resource::<Vec<StateAction>>()
+            .push(StateAction::EquipItem { item: item_data.flag });
+        Some(format!("you {} the {}", args.verb, display_name))
+    }
+}
+
+struct RemoveCommand {}
+
+impl TextCommand for RemoveCommand {
+    fn name(&self) -> &'static str {
+        "remove"
+    }
+
+    fn help(&self) -> &'static str {
+        "stop using something you wielded or wore"
+    }
+
+    fn takes_object(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String> {
+        let item_name = match &args.arg {
+            Some(item_name) => item_name,
+            None => return Some("remove what?".to_string()),
+        };
+
+        let equipments = world.read_storage::<Equipment>();
+        match find_item_by_name(item_name.as_str(), &world.fetch::<Vec<ItemData>>()) {
+            Some(item_data) if equipments.get(args.player).is_some_and(|equipment| equipment.has(item_data.flag)) => {
+                world
+                    .write_resource::<Vec<StateAction>>()
+                    .push(StateAction::UnequipItem { item: item_data.flag });
+                Some(format!("you remove the {}", item_data.name.to_ascii_lowercase()))
+            }
+            _ => Some(format!("you aren't using a {}", item_name)),
+        }
+    }
+}
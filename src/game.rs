@@ -1,9 +1,14 @@
+use rltk::DiceType;
 use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
 
+use crate::commands::{CommandArgs, CommandRegistry, TextCommand};
 use crate::components::*;
-use crate::items::ItemFlags;
+use crate::equipment::Equipment;
+use crate::items::{ItemData, ItemFlags};
 use crate::render::Renderable;
-use crate::textinput::*;
+use crate::room::{DynamicActionData, DynamicRoomData, LockedDoor, RoomData};
+use crate::visibility::{can_player_see, Viewshed};
 use crate::StateAction;
 
 pub struct DynamicMarker;
@@ -17,6 +22,18 @@ impl CurrentRoom {
     }
 }
 
+// total seconds spent playing, carried along in save games
+#[derive(Copy, Clone)]
+pub struct PlayTime(pub f32);
+
+// bare handed the player isn't much of a fighter, the sword makes up the difference
+fn base_player_damage() -> AppliesDamage {
+    AppliesDamage {
+        damage: DiceType::new(1, 4, 0),
+        hit_bonus: 2,
+    }
+}
+
 pub fn create_player_entity(world: &mut World) {
     world
         .create_entity()
@@ -24,23 +41,28 @@ pub fn create_player_entity(world: &mut World) {
         .with(PlayerInputComponent::new())
         .with(PlayerTextInputComponent::new())
         .with(Position { x: 12, y: 9 })
-        .with(Renderable::new_with_z('\u{2663}', rltk::WHITE, 1))
+        .with(Renderable::new_with_z('\u{2663}', rltk::WHITE, 2))
         .with(Player {})
         .with(Movement::new())
         .with(ColliderComponent {})
         .with(ActiveDescriptionComponent::new())
         .with(InventoryComponent::new())
+        .with(CarryCapacity {
+            max_weight: crate::inventory::DEFAULT_CARRY_WEIGHT,
+        })
+        .with(crate::visibility::Viewshed::new(crate::visibility::PLAYER_VIEW_RANGE))
         .with(DebugHudComponent {})
         .with(CombatStats {
             max_health: 10,
             health: 10,
         })
-        .with(AppliesDamage { damage: 10 })
+        .with(base_player_damage())
+        .with(Equipment::new())
         .with(DebugName {
             text: "player".to_string(),
         })
         .with(CombatLog::new())
-        .with(DebugHudComponent {})
+        .marked::<SimpleMarker<DynamicMarker>>()
         .build();
 }
 
@@ -124,6 +146,39 @@ impl<'a> System<'a> for ApplyPlayerMovementInputSystem {
     }
 }
 
+pub struct LockedDoorSystem {}
+
+impl LockedDoorSystem {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<'a> System<'a> for LockedDoorSystem {
+    type SystemData = (
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Movement>,
+        ReadStorage<'a, LockedDoor>,
+        WriteStorage<'a, ActiveDescriptionComponent>,
+    );
+
+    fn run(&mut self, (players, positions, movements, locked_doors, mut active_descriptions): Self::SystemData) {
+        for (_player, movement, position, active_description) in (&players, &movements, &positions, &mut active_descriptions).join() {
+            if movement.was_move_blocked() {
+                let (delta_x, delta_y) = movement.get_attempted_move();
+                let (target_x, target_y) = (position.x + delta_x, position.y + delta_y);
+                if (&locked_doors, &positions)
+                    .join()
+                    .any(|(_door, door_position)| door_position.equals_xy(target_x, target_y))
+                {
+                    active_description.set("The door is locked");
+                }
+            }
+        }
+    }
+}
+
 pub struct ExitTriggerSystem {}
 
 impl<'a> System<'a> for ExitTriggerSystem {
@@ -158,270 +213,410 @@ impl ExitTriggerSystem {
     }
 }
 
-pub struct PlayerTextCommandSystem {}
+// "a, b and c"
+fn join_names(names: &[String]) -> String {
+    match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        Some((last, _)) => last.clone(),
+        None => String::new(),
+    }
+}
+
+pub fn register_commands(registry: &mut CommandRegistry) {
+    registry.register(LookCommand {});
+    registry.register(UseCommand {});
+    registry.register(UnlockCommand {});
+    registry.register(PauseCommand {});
+    registry.register(QuitCommand {});
+}
 
-impl PlayerTextCommandSystem {
-    pub fn new() -> Self {
-        Self {}
+struct LookCommand {}
+
+impl TextCommand for LookCommand {
+    fn name(&self) -> &'static str {
+        "look"
     }
 
-    fn process_text_input<'a>(
-        &mut self,
-        current_room: CurrentRoom,
-        position: &Position,
-        inventory: &InventoryComponent,
-        text_command: &String,
-        descriptions: &ReadStorage<'a, Description>,
-        state_actions: &mut Vec<StateAction>,
-    ) -> Option<String> {
-        match parse_input(text_command) {
-            TextCommand::Some { command, arg } => match command.as_str() {
-                "look" => self.process_look(arg, descriptions),
-                "use" | "wave" | "show" | "play" => self.process_use(state_actions, current_room, position, inventory, command, arg),
-                "quit" => self.process_quit(state_actions),
-                _ => None,
-            },
-            TextCommand::None => None,
-        }
+    fn help(&self) -> &'static str {
+        "look around the room, or look <thing> to take a closer look"
     }
 
-    fn process_look<'a>(&self, look_at_target_name: Option<String>, descriptions: &ReadStorage<'a, Description>) -> Option<String> {
-        match look_at_target_name {
-            Some(target_name) => self.process_look_target(target_name.as_str(), descriptions),
-            _ => self.process_look_room(),
-        }
+    fn takes_object(&self) -> bool {
+        true
     }
 
-    fn process_look_target<'a>(&self, _target_name: &str, descriptions: &ReadStorage<'a, Description>) -> Option<String> {
-        for description in (descriptions).join() {
-            if description.input_name == _target_name {
-                return Some(description.description.clone());
-            }
-            if description.name.to_ascii_lowercase() == _target_name {
-                return Some(description.description.clone());
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String> {
+        match &args.arg {
+            Some(target_name) => {
+                let inventories = world.read_storage::<InventoryComponent>();
+                look_target(
+                    target_name.as_str(),
+                    inventories.get(args.player)?,
+                    &world.fetch::<Vec<ItemData>>(),
+                    &world.entities(),
+                    &world.read_storage::<Description>(),
+                    &world.read_storage::<ConditionalDescription>(),
+                )
             }
+            None => look_room(world, args.player),
         }
-        return Some("look at target".to_string());
-    }
-
-    fn process_look_room(&self) -> Option<String> {
-        return Some("look at room with long description here".to_string());
-    }
-
-    fn process_use(
-        &self,
-        state_actions: &mut Vec<StateAction>,
-        current_room: CurrentRoom,
-        position: &Position,
-        inventory: &InventoryComponent,
-        use_command: String,
-        maybe_use_target_name: Option<String>,
-    ) -> Option<String> {
-        if let Some(use_target_name) = maybe_use_target_name {
-            match use_target_name.as_str() {
-                "scepter" => return self.process_scepter(state_actions, current_room, inventory, use_command),
-                "wand" => return self.process_wand(state_actions, current_room, inventory, use_command),
-                "cross" => return self.process_cross(state_actions, current_room, inventory, use_command),
-                _ => {}
+    }
+}
+
+fn look_target<'a>(
+    target_name: &str,
+    inventory: &InventoryComponent,
+    item_datas: &Vec<ItemData>,
+    entities: &Entities<'a>,
+    descriptions: &ReadStorage<'a, Description>,
+    conditional_descriptions: &ReadStorage<'a, ConditionalDescription>,
+) -> Option<String> {
+    // carried items aren't in the room any more, so look them up first
+    if let Some(item_data) = crate::items::find_item_by_name(target_name, item_datas) {
+        if inventory.has(item_data.flag) {
+            let conditional_description = item_data
+                .conditional
+                .as_ref()
+                .map(|conditional| conditional.to_component(item_datas));
+            if let Some(conditional_text) = conditional_description
+                .as_ref()
+                .and_then(|conditional_description| conditional_description.get_description(inventory))
+            {
+                return Some(conditional_text.to_string());
             }
+            return Some(item_data.description.clone());
         }
-        None
     }
 
-    fn process_scepter(
-        &self,
-        state_actions: &mut Vec<StateAction>,
-        current_room: CurrentRoom,
-        inventory: &InventoryComponent,
-        use_command: String,
-    ) -> Option<String> {
-        // we need the scepter to continue
-        if inventory.has(ItemFlags::SCEPTER) == false {
-            return Some("you don't have a scepter".to_string());
+    // an entity with a conditional description wins over a plain one with the same name
+    let mut plain_description = None;
+    for (entity, description) in (entities, descriptions).join() {
+        if description.input_name != target_name && description.name.to_ascii_lowercase() != target_name {
+            continue;
         }
 
-        // make sure we're using the right action for it
-        match use_command.as_str() {
-            "use" | "wave" => {
-                let room_index = current_room.get_room_index();
-                if room_index == 0 {
-                    state_actions.push(StateAction::Victory);
-                    return Some("you win!".to_string());
-                } else {
-                    return Some("nothing happens".to_string());
-                }
-            }
-            _ => return Some("that doesn't work".to_string()),
+        if let Some(conditional_description) = conditional_descriptions.get(entity) {
+            let conditional_text = conditional_description
+                .get_description(inventory)
+                .unwrap_or(description.description.as_str());
+            return Some(conditional_text.to_string());
         }
+        plain_description.get_or_insert_with(|| description.description.clone());
     }
 
-    fn process_wand(
-        &self,
-        state_actions: &mut Vec<StateAction>,
-        current_room: CurrentRoom,
-        inventory: &InventoryComponent,
-        use_command: String,
-    ) -> Option<String> {
-        // we need the scepter to continue
-        if inventory.has(ItemFlags::MAGICWAND) == false {
-            return Some("you don't have a wand".to_string());
-        }
+    if plain_description.is_some() {
+        return plain_description;
+    }
+    return Some(format!("you don't see a {} here", target_name));
+}
 
-        match use_command.as_str() {
-            "use" | "wave" => {
-                let room_index = current_room.get_room_index();
-                // winding passage w/ secret
-                if room_index == 76 {
-                    state_actions.push(StateAction::RedirectRoom {
-                        original_room: 76,
-                        new_room: 83,
-                    });
-                    return Some("a secret passage opens!".to_string());
-                } else if room_index == 66 {
-                    state_actions.push(StateAction::RedirectRoom {
-                        original_room: 66,
-                        new_room: 84,
-                    });
-                    return Some("A secret passage opens!".to_string());
-                } else {
-                    return Some("nothing happens".to_string());
-                }
-            }
-            _ => return Some("that doesn't work".to_string()),
-        }
+// the room's long description, then whatever the player can see in it and the ways out
+fn look_room(world: &World, player: Entity) -> Option<String> {
+    let inventories = world.read_storage::<InventoryComponent>();
+    let room_data = crate::room::get_redirected_room_data(world, world.fetch::<CurrentRoom>().get_room_index());
+    if room_data.is_too_dark(Some(inventories.get(player)?)) {
+        return Some("It is too dark to see".to_string());
     }
 
-    fn process_cross(
-        &self,
-        state_actions: &mut Vec<StateAction>,
-        current_room: CurrentRoom,
-        inventory: &InventoryComponent,
-        use_command: String,
-    ) -> Option<String> {
-        // we need the scepter to continue
-        if inventory.has(ItemFlags::HOLYCROSS) == false {
-            return Some("you don't have a cross".to_string());
+    let players = world.read_storage::<Player>();
+    let viewsheds = world.read_storage::<Viewshed>();
+    let pickup_triggers = world.read_storage::<PickupTrigger>();
+    let combat_stats = world.read_storage::<CombatStats>();
+    let mut items = Vec::new();
+    let mut enemies = Vec::new();
+    for (entity, position, description) in (
+        &world.entities(),
+        &world.read_storage::<Position>(),
+        &world.read_storage::<Description>(),
+    )
+        .join()
+    {
+        if players.contains(entity) || !can_player_see(&players, &viewsheds, position) {
+            continue;
         }
-        match use_command.as_str() {
-            "use" | "show" => {
-                let room_index = current_room.get_room_index();
-                if room_index == 27 {
-                    return Some("vampire disappears".to_string());
-                } else {
-                    return Some("nothing happens".to_string());
-                }
-            }
-            _ => return Some("nothing happens".to_string()),
+
+        if pickup_triggers.contains(entity) {
+            items.push(format!("the {}", description.name));
+        } else if combat_stats.contains(entity) {
+            enemies.push(format!("the {}", description.name));
         }
     }
 
-    fn process_quit(&mut self, state_actions: &mut Vec<StateAction>) -> Option<String> {
-        state_actions.push(StateAction::Quit);
-        None
+    let mut sentences = vec![room_data.get_long_description()];
+    items.append(&mut enemies);
+    if !items.is_empty() {
+        sentences.push(format!("You see {}.", join_names(&items)));
+    }
+
+    let exits: Vec<String> = room_data
+        .get_exit_directions()
+        .iter()
+        .map(|direction| direction.name().to_string())
+        .collect();
+    match exits.len() {
+        0 => {}
+        1 => sentences.push(format!("There is an exit {}.", exits[0])),
+        _ => sentences.push(format!("There are exits {}.", join_names(&exits))),
+    }
+    return Some(sentences.join(" "));
+}
+
+struct UseCommand {}
+
+impl TextCommand for UseCommand {
+    fn name(&self) -> &'static str {
+        "use"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["wave", "show", "play", "give"]
+    }
+
+    fn help(&self) -> &'static str {
+        "use <item>, or give <item> to <someone>"
+    }
+
+    fn takes_object(&self) -> bool {
+        true
     }
 
-    fn process_debug_input(&mut self, text_command: &String, state_actions: &mut Vec<StateAction>) {
-        let mut tokens = text_command.split_whitespace();
-        match tokens.next() {
-            Some(token) => match token {
-                "go" => state_actions.push(self.process_debug_go(tokens.next())),
-                "dsave" => state_actions.push(StateAction::DebugSave),
-                "dload" => state_actions.push(StateAction::DebugLoad),
-                "redirect" => state_actions.push(self.process_debug_redirect(tokens.next(), tokens.next())),
-                _ => {}
-            },
-            None => {}
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String> {
+        if args.verb == "use" && args.arg.as_deref() == Some("key") {
+            // "use key" is the same as "unlock", the key isn't what gets unlocked
+            let unlock_args = CommandArgs {
+                player: args.player,
+                verb: "unlock".to_string(),
+                arg: None,
+                target: None,
+            };
+            return UnlockCommand {}.execute(world, &unlock_args);
         }
+
+        process_use(world, args.player, args.verb.as_str(), args.arg.clone(), args.target.clone())
     }
+}
 
-    fn process_debug_go(&mut self, to_room_text: Option<&str>) -> StateAction {
-        match to_room_text {
-            Some(to_room_str) => {
-                if let Ok(to_room) = to_room_str.parse::<i32>() {
-                    return StateAction::ChangeRoom {
-                        direction: crate::room::ExitDirection::Invalid,
-                        to_room: to_room,
-                    };
-                }
+fn process_use(
+    world: &World,
+    player: Entity,
+    use_command: &str,
+    maybe_use_target_name: Option<String>,
+    maybe_on_target_name: Option<String>,
+) -> Option<String> {
+    let use_target_name = match maybe_use_target_name {
+        Some(use_target_name) => use_target_name,
+        None => return Some(format!("{} what?", use_command)),
+    };
+
+    let inventories = world.read_storage::<InventoryComponent>();
+    let inventory = inventories.get(player)?;
+    let item_datas = world.fetch::<Vec<ItemData>>();
+    let room = world.fetch::<CurrentRoom>().get_room_index();
+    // the usables belong to the room itself rather than wherever it's been redirected to
+    let room_datas = world.fetch::<Vec<RoomData>>();
+    let room_data = &room_datas[room as usize];
+    let dynamic_room_datas = world.fetch::<Vec<DynamicRoomData>>();
+    let dynamic_room_data = &dynamic_room_datas[room as usize];
+    let mut state_actions = world.write_resource::<Vec<StateAction>>();
+
+    let carrying = |item_name: &str| match crate::items::find_item_by_name(item_name, &item_datas) {
+        Some(item_data) => inventory.has(item_data.flag),
+        None => false,
+    };
+
+    let mut wrong_verb = false;
+    let mut blocked_by_flag = false;
+    for usable in room_data.find_usables(use_target_name.as_str()) {
+        if usable.key != use_command {
+            wrong_verb = true;
+            continue;
+        }
+
+        // "give goblet to fairy", leaving off who it's for is fine but naming the wrong one isn't
+        if let (Some(usable_target), Some(on_target_name)) = (&usable.target, &maybe_on_target_name) {
+            if usable_target != on_target_name {
+                wrong_verb = true;
+                continue;
             }
-            _ => {}
         }
-        return StateAction::None;
-    }
 
-    fn process_debug_redirect(&mut self, original_room_text: Option<&str>, new_room_text: Option<&str>) -> StateAction {
-        if let Some(original_room) = original_room_text {
-            if let Some(new_room) = new_room_text {
-                return StateAction::RedirectRoom {
-                    original_room: original_room.parse::<i32>().unwrap(),
-                    new_room: new_room.parse::<i32>().unwrap(),
-                };
+        if let Some(required_item) = &usable.requires {
+            if !carrying(required_item.as_str()) {
+                return Some(format!("you don't have a {}", required_item));
             }
         }
 
-        return StateAction::None;
+        // the room's flags say whether it's ready to be used, or already has been
+        let flag_missing = usable.requires_flag.as_ref().is_some_and(|flag| !dynamic_room_data.has_flag(flag));
+        let flag_set = usable.unless_flag.as_ref().is_some_and(|flag| dynamic_room_data.has_flag(flag));
+        if flag_missing || flag_set {
+            blocked_by_flag = true;
+            continue;
+        }
+
+        for action in &usable.actions {
+            state_actions.push(dynamic_action_to_state_action(room, action, &item_datas));
+        }
+        return Some(usable.description.clone());
+    }
+
+    if blocked_by_flag {
+        return Some("nothing happens".to_string());
+    }
+
+    if wrong_verb {
+        return Some("that doesn't work".to_string());
+    }
+
+    // not something this room cares about, but it might still be an item
+    match crate::items::find_item_by_name(use_target_name.as_str(), &item_datas) {
+        Some(item_data) if carrying(use_target_name.as_str()) && item_data.consumable.is_some() => Some(crate::consumables::consume(
+            inventory,
+            &item_datas,
+            use_command,
+            use_target_name.as_str(),
+            &mut state_actions,
+        )),
+        Some(_) if carrying(use_target_name.as_str()) => Some("nothing happens".to_string()),
+        Some(_) => Some(format!("you don't have a {}", use_target_name)),
+        None => None,
     }
 }
 
-impl<'a> System<'a> for PlayerTextCommandSystem {
-    type SystemData = (
-        Entities<'a>,
-        ReadExpect<'a, CurrentRoom>,
-        ReadStorage<'a, Player>,
-        ReadStorage<'a, Position>,
-        ReadStorage<'a, InventoryComponent>,
-        WriteStorage<'a, PlayerTextInputComponent>,
-        WriteStorage<'a, ActiveDescriptionComponent>,
-        ReadStorage<'a, Description>,
-        ReadStorage<'a, DebugHudComponent>,
-        WriteExpect<'a, Vec<StateAction>>,
-    );
+fn dynamic_action_to_state_action(room: i32, action: &DynamicActionData, item_datas: &Vec<ItemData>) -> StateAction {
+    match action {
+        DynamicActionData::GiveItem { item } => match crate::items::find_item_by_name(item.as_str(), item_datas) {
+            Some(item_data) => StateAction::GiveItem { item: item_data.flag },
+            None => StateAction::None,
+        },
+        DynamicActionData::AddFlag { flag } => StateAction::AddRoomFlag {
+            room: room,
+            flag: flag.clone(),
+        },
+        DynamicActionData::UpdateMap { x, y, tile } => StateAction::UpdateMap {
+            room: room,
+            x: *x,
+            y: *y,
+            tile: tile.unwrap_or(32),
+        },
+        DynamicActionData::BanishEnemy { name } => StateAction::BanishEnemy {
+            room: room,
+            name: name.clone(),
+        },
+        DynamicActionData::RedirectRoom { new_room } => StateAction::RedirectRoom {
+            original_room: room,
+            new_room: *new_room,
+        },
+        DynamicActionData::Victory => StateAction::Victory,
+    }
+}
 
-    fn run(
-        &mut self,
-        (
-            entities,
-            current_room,
-            players,
-            positions,
-            inventories,
-            mut text_inputs,
-            mut active_descriptions,
-            descriptions,
-            debugs,
-            mut _state_actions,
-        ): Self::SystemData,
-    ) {
-        for (entity, _player, position, inventory, text_input, description) in (
-            &entities,
-            &players,
-            &positions,
-            &inventories,
-            &mut text_inputs,
-            &mut active_descriptions,
-        )
-            .join()
-        {
-            match text_input.consume() {
-                Some(text_command) => {
-                    match self.process_text_input(
-                        *current_room,
-                        &position,
-                        &inventory,
-                        &text_command,
-                        &descriptions,
-                        &mut _state_actions,
-                    ) {
-                        Some(result) => description.set(result.as_str()),
-                        None => description.set("i don't understand"),
-                    }
+struct UnlockCommand {}
 
-                    if let Some(_debug) = debugs.get(entity) {
-                        self.process_debug_input(&text_command, &mut _state_actions);
-                    }
-                }
-                None => {}
+impl TextCommand for UnlockCommand {
+    fn name(&self) -> &'static str {
+        "unlock"
+    }
+
+    fn help(&self) -> &'static str {
+        "unlock the door next to you, if you have the key"
+    }
+
+    fn takes_object(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String> {
+        process_unlock(world, args.player, args.arg.clone())
+    }
+}
+
+fn process_unlock(world: &World, player: Entity, maybe_target_name: Option<String>) -> Option<String> {
+    if let Some(target_name) = maybe_target_name {
+        if target_name != "door" {
+            return Some(format!("you can't unlock the {}", target_name));
+        }
+    }
+
+    let inventories = world.read_storage::<InventoryComponent>();
+    if !inventories.get(player)?.has(ItemFlags::KEY) {
+        return Some("you don't have a key".to_string());
+    }
+
+    // start with any door next to the player, then take every door tile touching those so wide doors open together
+    let positions = world.read_storage::<Position>();
+    let position = positions.get(player)?;
+    let door_positions: Vec<Position> = (&world.read_storage::<LockedDoor>(), &positions)
+        .join()
+        .map(|(_door, door_position)| door_position.clone())
+        .collect();
+    let mut doors_to_open: Vec<Position> = door_positions
+        .iter()
+        .filter(|door_position| Position::distance_sq(position, door_position) <= 2)
+        .cloned()
+        .collect();
+    if doors_to_open.is_empty() {
+        return Some("there is no door here".to_string());
+    }
+
+    let mut index = 0;
+    while index < doors_to_open.len() {
+        let door = doors_to_open[index].clone();
+        for door_position in door_positions.iter() {
+            if Position::distance_sq(&door, door_position) <= 2 && !doors_to_open.contains(door_position) {
+                doors_to_open.push(door_position.clone());
             }
         }
+        index += 1;
+    }
+
+    let room = world.fetch::<CurrentRoom>().get_room_index();
+    let mut state_actions = world.write_resource::<Vec<StateAction>>();
+    for door in doors_to_open {
+        state_actions.push(StateAction::UpdateMap {
+            room: room,
+            x: door.x,
+            y: door.y,
+            tile: 32,
+        });
+    }
+    Some("the door unlocks".to_string())
+}
+
+struct PauseCommand {}
+
+impl TextCommand for PauseCommand {
+    fn name(&self) -> &'static str {
+        "pause"
+    }
+
+    fn help(&self) -> &'static str {
+        "pause the game"
+    }
+
+    fn execute(&self, world: &mut World, _args: &CommandArgs) -> Option<String> {
+        world.write_resource::<Vec<StateAction>>().push(StateAction::Pause);
+        Some("".to_string())
+    }
+}
+
+struct QuitCommand {}
+
+impl TextCommand for QuitCommand {
+    fn name(&self) -> &'static str {
+        "quit"
+    }
+
+    fn help(&self) -> &'static str {
+        "quit the game"
+    }
+
+    fn needs_exact_name(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, world: &mut World, _args: &CommandArgs) -> Option<String> {
+        world.write_resource::<Vec<StateAction>>().push(StateAction::Quit);
+        None
     }
 }
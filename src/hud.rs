@@ -2,8 +2,10 @@ use specs::prelude::*;
 
 use crate::components::*;
 use crate::game::CurrentRoom;
+use crate::items::ItemData;
 use crate::render::Renderable;
 use crate::room::RoomData;
+use crate::visibility::{can_player_see, Viewshed};
 
 #[allow(dead_code)]
 pub struct HudSystem<'a> {
@@ -33,7 +35,12 @@ impl<'a> HudSystem<'a> {
         draw_border_piece(self.context, 24, 18, '┘');
     }
 
-    fn print_description(&mut self, room_data: &RoomData) {
+    fn print_description(&mut self, room_data: &RoomData, too_dark: bool) {
+        if too_dark {
+            self.context.print(0, 19, "It is too dark to see");
+            return;
+        }
+
         for row in 0..5 {
             self.context.print(0, 19 + row, &room_data.description[row]);
         }
@@ -47,25 +54,29 @@ impl<'a> HudSystem<'a> {
     fn print_action_result(&mut self, active_description: &String) {
         let start_x = 25;
         let start_y = 11;
+        let combat_log_y = 17;
 
-        let mut current_x = start_x;
-        let mut current_y = start_y;
-        let tokens = active_description.split_whitespace();
-        for token in tokens {
-            let should_add_space = current_x != start_x;
-            let text_length = token.len();
-            let mut space_length = if should_add_space { 1 } else { 0 };
+        let mut lines: Vec<String> = vec![String::new()];
+        for token in active_description.split_whitespace() {
+            let current_line = lines.last_mut().unwrap();
+            let space_length = if current_line.is_empty() { 0 } else { 1 };
 
             // check to see if we should wrap to the next line
-            if (current_x + text_length + space_length) >= 40 {
-                current_y += 1;
-                current_x = start_x;
+            if !current_line.is_empty() && (start_x + current_line.len() + token.len() + space_length) >= 40 {
+                lines.push(token.to_string());
+                continue;
+            }
 
-                space_length = 0;
+            if space_length > 0 {
+                current_line.push(' ');
             }
+            current_line.push_str(token);
+        }
 
-            self.context.print(current_x + space_length, current_y, token);
-            current_x += text_length + space_length;
+        // long results (ie. looking at the room) grow upwards over the glyph list, blanking out what's underneath
+        let start_y = usize::min(start_y, combat_log_y - usize::min(lines.len(), combat_log_y));
+        for (row, line) in lines.iter().enumerate() {
+            self.context.print(start_x, start_y + row, format!("{:<15}", line));
         }
     }
 
@@ -79,12 +90,53 @@ impl<'a> HudSystem<'a> {
         }
     }
 
-    fn print_glyph_descriptions(&mut self, renderables: &ReadStorage<'a, Renderable>, descriptions: &ReadStorage<'a, Description>) {
+    fn print_inventory(&mut self, inventory: &InventoryComponent, item_datas: &Vec<ItemData>) {
+        let start_x = 25;
+        let start_y = 0;
+        let max_rows = 11;
+
+        self.context.print(start_x, start_y, "Inventory");
+        let mut current_y = start_y + 1;
+
+        let items = inventory.items().each();
+        for (index, item) in items.iter().enumerate() {
+            // leave the last row for a count of whatever didn't fit
+            if current_y == start_y + max_rows - 1 && index < items.len() - 1 {
+                self.context.print(start_x, current_y, format!("+{} more", items.len() - index));
+                break;
+            }
+
+            if let Some(item_data) = crate::items::find_item(*item, item_datas) {
+                self.context.set(
+                    start_x,
+                    current_y,
+                    rltk::RGB::named(rltk::WHITE),
+                    rltk::RGB::named(rltk::BLACK),
+                    rltk::to_cp437(item_data.glyph),
+                );
+                self.context.print(start_x + 2, current_y, item_data.name.as_str());
+                current_y += 1;
+            }
+        }
+    }
+
+    fn print_glyph_descriptions(
+        &mut self,
+        players: &ReadStorage<'a, Player>,
+        viewsheds: &ReadStorage<'a, Viewshed>,
+        positions: &ReadStorage<'a, Position>,
+        renderables: &ReadStorage<'a, Renderable>,
+        descriptions: &ReadStorage<'a, Description>,
+    ) {
         let start_x = 25;
         let start_y = 0;
         let mut current_y = start_y;
 
-        for (renderable, description) in (renderables, descriptions).join() {
+        for (position, renderable, description) in (positions, renderables, descriptions).join() {
+            if !can_player_see(players, viewsheds, position) {
+                continue;
+            }
+
             self.context.set(
                 start_x,
                 current_y,
@@ -118,31 +170,63 @@ impl<'a> System<'a> for HudSystem<'a> {
         ReadStorage<'a, CombatLog>,
         ReadStorage<'a, Renderable>,
         ReadStorage<'a, Description>,
+        ReadStorage<'a, InventoryComponent>,
+        ReadStorage<'a, InventoryHudComponent>,
+        ReadExpect<'a, Vec<ItemData>>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Viewshed>,
     );
 
     fn run(
         &mut self,
-        (current_room, _players, player_text_inputs, active_descriptions, room_datas, combat_logs, renderables, descriptions): Self::SystemData,
+        (
+            current_room,
+            players,
+            player_text_inputs,
+            active_descriptions,
+            room_datas,
+            combat_logs,
+            renderables,
+            descriptions,
+            inventories,
+            inventory_huds,
+            item_datas,
+            positions,
+            viewsheds,
+        ): Self::SystemData,
     ) {
         self.draw_map_border();
 
         let room_index: i32 = (*current_room).get_room_index();
         let room_data = &room_datas[room_index as usize];
-        self.print_description(room_data);
+        let too_dark = (&players, &inventories)
+            .join()
+            .any(|(_player, inventory)| room_data.is_too_dark(Some(inventory)));
+        self.print_description(room_data, too_dark);
 
         for player_input in player_text_inputs.join() {
             self.print_input_text(&player_input.get_preview());
         }
 
-        for active_description in active_descriptions.join() {
-            self.print_action_result(&active_description.description);
-        }
-
         for combat_log in combat_logs.join() {
             self.print_combat_logs(&combat_log);
         }
 
-        self.print_glyph_descriptions(&renderables, &descriptions);
+        // the inventory panel takes the place of the room's glyph list
+        let mut showed_inventory = false;
+        for (_player, inventory, _inventory_hud) in (&players, &inventories, &inventory_huds).join() {
+            self.print_inventory(inventory, &item_datas);
+            showed_inventory = true;
+        }
+
+        if !showed_inventory {
+            self.print_glyph_descriptions(&players, &viewsheds, &positions, &renderables, &descriptions);
+        }
+
+        // last, so a long result can cover the list above it
+        for active_description in active_descriptions.join() {
+            self.print_action_result(&active_description.description);
+        }
     }
 }
 
@@ -1,8 +1,128 @@
 use specs::prelude::*;
 
-use crate::components::{InventoryComponent, Movement, PickupTrigger, Player, Position};
+use crate::commands::{CommandArgs, CommandRegistry, TextCommand};
+use crate::components::{
+    ActiveDescriptionComponent, CarryCapacity, Description, InventoryComponent, InventoryHudComponent, Movement, PickupTrigger, Player,
+    Position,
+};
+use crate::equipment::Equipment;
+use crate::game::CurrentRoom;
+use crate::items::{ItemData, ItemFlags};
+use crate::score::{Score, ScoreValue};
 use crate::StateAction;
 
+pub const DEFAULT_CARRY_WEIGHT: i32 = 20;
+
+pub fn carried_weight(inventory: &InventoryComponent, item_datas: &Vec<ItemData>) -> i32 {
+    inventory
+        .items()
+        .each()
+        .iter()
+        .filter_map(|item| crate::items::find_item(*item, item_datas))
+        .map(|item_data| item_data.get_weight())
+        .sum()
+}
+
+// anything without a capacity can carry as much as it likes
+pub fn can_carry(inventory: &InventoryComponent, capacity: Option<&CarryCapacity>, item: ItemFlags, item_datas: &Vec<ItemData>) -> bool {
+    match (capacity, crate::items::find_item(item, item_datas)) {
+        (Some(capacity), Some(item_data)) => carried_weight(inventory, item_datas) + item_data.get_weight() <= capacity.max_weight,
+        _ => true,
+    }
+}
+
+fn collect_item(inventory: &mut InventoryComponent, item: ItemFlags, score_value: Option<&ScoreValue>, score: &mut Score) {
+    inventory.add(item);
+    if let Some(score_value) = score_value {
+        score.collect(item, score_value.points);
+    }
+}
+
+// picks up an item the player isn't standing on
+pub fn take_item(world: &mut World, item_entity: Entity) {
+    {
+        let pickup_triggers = world.read_storage::<PickupTrigger>();
+        let score_values = world.read_storage::<ScoreValue>();
+        let mut score = world.write_resource::<Score>();
+        if let Some(pickup_trigger) = pickup_triggers.get(item_entity) {
+            for (_player, inventory) in (&world.read_storage::<Player>(), &mut world.write_storage::<InventoryComponent>()).join() {
+                collect_item(inventory, pickup_trigger.item_to_pickup, score_values.get(item_entity), &mut score);
+            }
+        }
+    }
+    world.delete_entity(item_entity).expect("failed to delete taken item");
+}
+
+// hands an item straight to the player, ie. as a puzzle reward. if it's too heavy it lands at their feet instead
+pub fn give_item(world: &mut World, item: ItemFlags) {
+    let points = crate::items::find_item(item, &world.fetch::<Vec<ItemData>>()).and_then(|item_data| item_data.points);
+    let score_value = points.map(|points| ScoreValue { points: points });
+
+    let mut drop_position = None;
+    {
+        let item_datas = world.fetch::<Vec<ItemData>>();
+        let carry_capacities = world.read_storage::<CarryCapacity>();
+        let mut score = world.write_resource::<Score>();
+        for (entity, _player, position, inventory, active_description) in (
+            &world.entities(),
+            &world.read_storage::<Player>(),
+            &world.read_storage::<Position>(),
+            &mut world.write_storage::<InventoryComponent>(),
+            &mut world.write_storage::<ActiveDescriptionComponent>(),
+        )
+            .join()
+        {
+            if can_carry(inventory, carry_capacities.get(entity), item, &item_datas) {
+                collect_item(inventory, item, score_value.as_ref(), &mut score);
+                continue;
+            }
+
+            let item_name =
+                crate::items::find_item(item, &item_datas).map_or("item".to_string(), |item_data| item_data.name.to_ascii_lowercase());
+            let too_heavy = format!("the {} is too heavy to carry, it's on the floor", item_name);
+            let description = if active_description.description.is_empty() {
+                too_heavy
+            } else {
+                format!("{} {}", active_description.description, too_heavy)
+            };
+            active_description.set(description.as_str());
+            drop_position = Some(position.clone());
+        }
+    }
+
+    if let Some(position) = drop_position {
+        let room = world.fetch::<CurrentRoom>().get_room_index();
+        let item_name = crate::items::get_item_name(item, world);
+        crate::items::create_item_by_name(world, room, item_name.as_str(), position.x, position.y);
+    }
+}
+
+// puts an item from the player's inventory back into the room where they're standing
+pub fn drop_item(world: &mut World, item: ItemFlags) {
+    let mut drop_position = None;
+    for (_player, position, inventory) in (
+        &world.read_storage::<Player>(),
+        &world.read_storage::<Position>(),
+        &mut world.write_storage::<InventoryComponent>(),
+    )
+        .join()
+    {
+        if inventory.has(item) {
+            inventory.remove(item);
+            drop_position = Some(position.clone());
+        }
+    }
+
+    // anything put down can't still be in use
+    crate::equipment::unequip_item(world, item);
+
+    if let Some(position) = drop_position {
+        let room = world.fetch::<CurrentRoom>().get_room_index();
+        let item_name = crate::items::get_item_name(item, world);
+        crate::items::create_item_by_name(world, room, item_name.as_str(), position.x, position.y);
+    }
+}
+
 pub struct PickupTriggerSystem {}
 
 impl<'a> System<'a> for PickupTriggerSystem {
@@ -13,17 +133,58 @@ impl<'a> System<'a> for PickupTriggerSystem {
         ReadStorage<'a, Movement>,
         ReadStorage<'a, PickupTrigger>,
         WriteStorage<'a, InventoryComponent>,
+        ReadStorage<'a, CarryCapacity>,
+        WriteStorage<'a, ActiveDescriptionComponent>,
+        ReadStorage<'a, ScoreValue>,
+        WriteExpect<'a, Score>,
+        ReadExpect<'a, Vec<ItemData>>,
         WriteExpect<'a, Vec<StateAction>>,
     );
 
-    fn run(&mut self, (entities, players, positions, movements, pickup_triggers, mut inventories, mut state_actions): Self::SystemData) {
+    fn run(
+        &mut self,
+        (
+            entities,
+            players,
+            positions,
+            movements,
+            pickup_triggers,
+            mut inventories,
+            carry_capacities,
+            mut active_descriptions,
+            score_values,
+            mut score,
+            item_datas,
+            mut state_actions,
+        ): Self::SystemData,
+    ) {
         let mut picked_up_items: Vec<Entity> = Vec::new();
         {
-            for (_player, movement, position, inventory) in (&players, &movements, &positions, &mut inventories).join() {
+            for (player_entity, _player, movement, position, inventory) in
+                (&entities, &players, &movements, &positions, &mut inventories).join()
+            {
                 if movement.did_move() {
                     for (pickup_entity, pickup_trigger, pickup_position) in (&entities, &pickup_triggers, &positions).join() {
                         if position == pickup_position {
-                            inventory.add(pickup_trigger.item_to_pickup);
+                            // too heavy, the item stays where it is
+                            if !can_carry(
+                                inventory,
+                                carry_capacities.get(player_entity),
+                                pickup_trigger.item_to_pickup,
+                                &item_datas,
+                            ) {
+                                if let Some(active_description) = active_descriptions.get_mut(player_entity) {
+                                    active_description.set("you can't carry any more, drop something first");
+                                }
+                                break;
+                            }
+
+                            collect_item(
+                                inventory,
+                                pickup_trigger.item_to_pickup,
+                                score_values.get(pickup_entity),
+                                &mut score,
+                            );
                             picked_up_items.push(pickup_entity);
                             break;
                         }
@@ -43,3 +204,165 @@ impl PickupTriggerSystem {
         Self {}
     }
 }
+
+pub fn register_commands(registry: &mut CommandRegistry) {
+    registry.register(InventoryCommand {});
+    registry.register(TakeCommand {});
+    registry.register(DropCommand {});
+}
+
+struct InventoryCommand {}
+
+impl TextCommand for InventoryCommand {
+    fn name(&self) -> &'static str {
+        "inventory"
+    }
+
+    fn help(&self) -> &'static str {
+        "list what you are carrying, inventory panel shows or hides the list"
+    }
+
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String> {
+        // "i panel" toggles the inventory list in the hud
+        if let Some(option) = &args.arg {
+            if option != "panel" {
+                return None;
+            }
+            let mut inventory_huds = world.write_storage::<InventoryHudComponent>();
+            if inventory_huds.contains(args.player) {
+                inventory_huds.remove(args.player);
+                return Some("inventory panel hidden".to_string());
+            }
+            inventory_huds
+                .insert(args.player, InventoryHudComponent {})
+                .expect("failed to add inventory hud");
+            return Some("inventory panel shown".to_string());
+        }
+
+        let inventories = world.read_storage::<InventoryComponent>();
+        let equipments = world.read_storage::<Equipment>();
+        let item_datas = world.fetch::<Vec<ItemData>>();
+        let inventory = inventories.get(args.player)?;
+        let item_names: Vec<String> = inventory
+            .items()
+            .each()
+            .iter()
+            .filter_map(|item| crate::items::find_item(*item, &item_datas))
+            .map(|item_data| match equipments.get(args.player) {
+                Some(equipment) if equipment.has(item_data.flag) => format!("{} (in use)", item_data.name),
+                _ => item_data.name.clone(),
+            })
+            .collect();
+        if item_names.is_empty() {
+            return Some("you aren't carrying anything".to_string());
+        }
+        Some(format!("you are carrying: {}", item_names.join(", ")))
+    }
+}
+
+struct TakeCommand {}
+
+impl TextCommand for TakeCommand {
+    fn name(&self) -> &'static str {
+        "take"
+    }
+
+    fn help(&self) -> &'static str {
+        "take <item> from next to you"
+    }
+
+    fn takes_object(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String> {
+        let item_name = match &args.arg {
+            Some(item_name) => item_name,
+            None => return Some("take what?".to_string()),
+        };
+
+        let positions = world.read_storage::<Position>();
+        let inventories = world.read_storage::<InventoryComponent>();
+        let position = positions.get(args.player)?;
+        let inventory = inventories.get(args.player)?;
+        let carry_capacities = world.read_storage::<CarryCapacity>();
+        let item_datas = world.fetch::<Vec<ItemData>>();
+
+        let mut out_of_reach = false;
+        for (item_entity, pickup_trigger, item_position, description) in (
+            &world.entities(),
+            &world.read_storage::<PickupTrigger>(),
+            &positions,
+            &world.read_storage::<Description>(),
+        )
+            .join()
+        {
+            if &description.input_name != item_name && &description.name.to_ascii_lowercase() != item_name {
+                continue;
+            }
+
+            // only things right next to the player can be reached
+            if Position::distance_sq(position, item_position) > 2 {
+                out_of_reach = true;
+                continue;
+            }
+
+            if !can_carry(
+                inventory,
+                carry_capacities.get(args.player),
+                pickup_trigger.item_to_pickup,
+                &item_datas,
+            ) {
+                return Some(format!(
+                    "the {} is too heavy, drop something first",
+                    description.name.to_ascii_lowercase()
+                ));
+            }
+
+            world
+                .write_resource::<Vec<StateAction>>()
+                .push(StateAction::TakeItem { item_entity });
+            return Some(format!("you take the {}", description.name.to_ascii_lowercase()));
+        }
+
+        if out_of_reach {
+            return Some(format!("the {} is too far away", item_name));
+        }
+        Some(format!("there is no {} here", item_name))
+    }
+}
+
+struct DropCommand {}
+
+impl TextCommand for DropCommand {
+    fn name(&self) -> &'static str {
+        "drop"
+    }
+
+    fn help(&self) -> &'static str {
+        "drop <item> where you are standing"
+    }
+
+    fn takes_object(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String> {
+        let item_name = match &args.arg {
+            Some(item_name) => item_name,
+            None => return Some("drop what?".to_string()),
+        };
+
+        let inventories = world.read_storage::<InventoryComponent>();
+        let inventory = inventories.get(args.player)?;
+        match crate::items::find_item_by_name(item_name.as_str(), &world.fetch::<Vec<ItemData>>()) {
+            Some(item_data) if inventory.has(item_data.flag) => {
+                world
+                    .write_resource::<Vec<StateAction>>()
+                    .push(StateAction::DropItem { item: item_data.flag });
+                Some(format!("you drop the {}", item_data.name.to_ascii_lowercase()))
+            }
+            _ => Some(format!("you don't have a {}", item_name)),
+        }
+    }
+}
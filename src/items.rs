@@ -4,8 +4,11 @@ use specs::prelude::*;
 use specs::saveload::*;
 use std::fs::File;
 
-use crate::components::{Description, PickupTrigger, Position};
+use crate::components::{Condition, ConditionalDescription, Description, PickupTrigger, Position};
+use crate::consumables::ConsumableData;
+use crate::equipment::EquipmentData;
 use crate::game::DynamicMarker;
+use crate::score::ScoreValue;
 
 // mostly only used in inventory now, should probably look at getting rid of this somehow
 bitflags! {
@@ -35,6 +38,24 @@ bitflags! {
         const FANCYGOBLET   = 1 << 20;      // 00B5
         const CROWN         = 1 << 21;      // 2302
         const ANYTHING      = 0b1111111111111111111111111111111;
+
+        const TREASURES     = Self::DIAMOND.bits | Self::SILVERBARS.bits | Self::RUBIES.bits | Self::JADEFIGURINE.bits
+                            | Self::HARP.bits | Self::HOURGLASS.bits | Self::LARGEGEM.bits | Self::GOLDBAR.bits
+                            | Self::FANCYGOBLET.bits | Self::CROWN.bits;
+    }
+}
+
+impl ItemFlags {
+    // splits a set of flags into the individual items
+    pub fn each(&self) -> Vec<ItemFlags> {
+        let mut items = Vec::new();
+        for bit in 0..32 {
+            let item = ItemFlags::from_bits_truncate(1 << bit);
+            if !item.is_empty() && self.contains(item) {
+                items.push(item);
+            }
+        }
+        items
     }
 }
 
@@ -55,9 +76,14 @@ pub fn create_item_by_name(world: &mut World, room: i32, item_name: &str, x: i32
 }
 
 fn spawn_item(world: &mut World, room: i32, item: &ItemData, x: i32, y: i32) {
+    let conditional_description = item
+        .conditional
+        .as_ref()
+        .map(|conditional| conditional.to_component(&world.fetch::<Vec<ItemData>>()));
+
     match item.flag {
         _ => {
-            world
+            let mut entity = world
                 .create_entity()
                 .with(Position { x: x, y: y })
                 .with(crate::render::Renderable::new_with_z(item.glyph, rltk::WHITE, 1))
@@ -68,8 +94,17 @@ fn spawn_item(world: &mut World, room: i32, item: &ItemData, x: i32, y: i32) {
                 } else {
                     Description::new(&item.name, &item.description)
                 })
-                .marked::<SimpleMarker<DynamicMarker>>()
-                .build();
+                .marked::<SimpleMarker<DynamicMarker>>();
+
+            if let Some(points) = item.points {
+                entity = entity.with(ScoreValue { points: points });
+            }
+
+            if let Some(conditional_description) = conditional_description {
+                entity = entity.with(conditional_description);
+            }
+
+            entity.build();
         }
     }
 }
@@ -83,7 +118,13 @@ pub fn get_item_name(item_to_find: ItemFlags, world: &World) -> String {
     return item.name.clone();
 }
 
-fn find_item<'a>(item_to_find: ItemFlags, items: &'a Vec<ItemData>) -> Option<&'a ItemData> {
+pub fn get_item_display_name(item_to_find: ItemFlags, world: &World) -> String {
+    let items = world.fetch::<Vec<ItemData>>();
+    let item = find_item(item_to_find, &items).expect("failed to find item");
+    item.name.clone()
+}
+
+pub fn find_item<'a>(item_to_find: ItemFlags, items: &'a Vec<ItemData>) -> Option<&'a ItemData> {
     for item in items {
         if item.flag == item_to_find {
             return Some(item);
@@ -92,7 +133,7 @@ fn find_item<'a>(item_to_find: ItemFlags, items: &'a Vec<ItemData>) -> Option<&'
     return None;
 }
 
-fn find_item_by_name<'a>(item_to_find: &str, items: &'a Vec<ItemData>) -> Option<&'a ItemData> {
+pub fn find_item_by_name<'a>(item_to_find: &str, items: &'a Vec<ItemData>) -> Option<&'a ItemData> {
     for item in items {
         if let Some(input_name) = &item.input_name {
             if input_name == item_to_find {
@@ -107,12 +148,42 @@ fn find_item_by_name<'a>(item_to_find: &str, items: &'a Vec<ItemData>) -> Option
 }
 
 #[derive(Serialize, Deserialize, Clone)]
-struct ItemData {
+pub struct ItemData {
     pub flag: ItemFlags,
     pub name: String,
     pub input_name: Option<String>,
     pub description: String,
     pub glyph: char,
+    pub points: Option<i32>,
+    pub weight: Option<i32>, // defaults to 1
+    pub conditional: Option<ConditionalDescriptionData>,
+    pub equipment: Option<EquipmentData>,   // only for things that can be wielded or worn
+    pub consumable: Option<ConsumableData>, // only for things that get used up
+}
+
+// shared by anything with a description (items, enemies)
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ConditionalDescriptionData {
+    pub requires: String, // item name
+    pub description: String,
+}
+
+impl ConditionalDescriptionData {
+    pub fn to_component(&self, items: &Vec<ItemData>) -> ConditionalDescription {
+        let required_item = find_item_by_name(self.requires.as_str(), items).expect("failed to find required item");
+        ConditionalDescription {
+            condition: Condition {
+                requires: required_item.flag,
+            },
+            description: self.description.clone(),
+        }
+    }
+}
+
+impl ItemData {
+    pub fn get_weight(&self) -> i32 {
+        self.weight.unwrap_or(1)
+    }
 }
 
 pub fn load_items(world: &mut World) {
@@ -134,6 +205,11 @@ fn save_items() {
         input_name: Some("lamp".to_string()),
         description: "It's bright!".to_string(),
         glyph: '\u{2660}',
+        points: None,
+        weight: Some(1),
+        conditional: None,
+        equipment: None,
+        consumable: None,
     };
     items.push(item);
 
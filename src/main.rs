@@ -4,22 +4,43 @@ use specs::saveload::{SimpleMarker, SimpleMarkerAllocator};
 
 mod ai;
 mod combat;
+mod commands;
 mod components;
+mod consumables;
+mod debug;
 mod enemies;
+mod equipment;
 mod game;
 mod hud;
 mod input;
 mod inventory;
 mod items;
+mod menu;
+mod random;
 mod render;
 mod room;
+mod saveload;
+mod score;
 mod textinput;
+mod travel;
+mod visibility;
 
 use crate::components::*;
-use crate::game::CurrentRoom;
+use crate::game::{CurrentRoom, PlayTime};
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum RunState {
+    Title { selected: menu::TitleOption },
+    Playing,
+    Paused,
+    GameOver { selected: menu::GameOverOption },
+    Victory,
+}
 
 pub struct State {
     world: World,
+    run_state: RunState,
+    menu_error: Option<String>, // shown on the menus until the next choice is made
 }
 
 #[derive(Clone)]
@@ -28,14 +49,32 @@ pub enum StateAction {
     DeleteEntities { entities: Vec<Entity> },
     ChangeRoom { direction: room::ExitDirection, to_room: i32 },
     RedirectRoom { original_room: i32, new_room: i32 },
+    TakeItem { item_entity: Entity },
+    DropItem { item: items::ItemFlags },
+    GiveItem { item: items::ItemFlags },
+    EquipItem { item: items::ItemFlags },
+    UnequipItem { item: items::ItemFlags },
+    ConsumeItem { item: items::ItemFlags },
+    AddRoomFlag { room: i32, flag: String },
+    UpdateMap { room: i32, x: i32, y: i32, tile: u8 },
+    BanishEnemy { room: i32, name: String },
     Victory,
+    PlayerDied { cause: String },
+    Pause,
     Quit,
+    SaveGame { slot: String },
+    LoadGame { slot: String },
     DebugSave,
     DebugLoad,
 }
 
 impl State {
     fn run_systems(&mut self, context: &mut Rltk) {
+        self.world.fetch_mut::<PlayTime>().0 += context.frame_time_ms / 1000.0;
+
+        let mut visibility_system = visibility::VisibilitySystem {};
+        visibility_system.run_now(&self.world);
+
         // requires mutable context
         self.draw_entities(context);
         self.draw_hud(context);
@@ -45,15 +84,17 @@ impl State {
         let mut player_input_system = input::PlayerInputSystem::new(context);
         player_input_system.run_now(&self.world);
 
+        // travelling stands in for the movement keys
+        let mut travel_system = travel::TravelSystem::new(context.frame_time_ms);
+        travel_system.run_now(&self.world);
+
         let mut apply_player_movement_input = game::ApplyPlayerMovementInputSystem::new();
         apply_player_movement_input.run_now(&self.world);
         if apply_player_movement_input.player_moved {
-            let mut ai = ai::AiMoveToPlayerSystem {};
-            ai.run_now(&self.world);
+            self.run_ai_systems();
         }
 
-        let mut player_commands = game::PlayerTextCommandSystem::new();
-        player_commands.run_now(&self.world);
+        commands::process_text_commands(&mut self.world);
         self.handle_pending_state_actions();
 
         let mut movement_system = game::MovementSystem::new();
@@ -67,9 +108,15 @@ impl State {
             let mut melee_system = combat::MeleeCombatSystem {};
             melee_system.run_now(&self.world);
 
+            let mut locked_doors = game::LockedDoorSystem::new();
+            locked_doors.run_now(&self.world);
+
             let mut damage_system = combat::DamageSystem {};
             damage_system.run_now(&self.world);
 
+            let mut buff_system = consumables::BuffSystem {};
+            buff_system.run_now(&self.world);
+
             let mut dead_system = combat::ClearDeadSystem::new();
             dead_system.run_now(&self.world);
             self.handle_pending_state_actions();
@@ -82,6 +129,23 @@ impl State {
         self.world.maintain();
     }
 
+    fn run_ai_systems(&mut self) {
+        let mut chase = ai::AiMoveToPlayerSystem {};
+        chase.run_now(&self.world);
+
+        let mut wander = ai::AiWanderSystem {};
+        wander.run_now(&self.world);
+
+        let mut ambush = ai::AiAmbushSystem {};
+        ambush.run_now(&self.world);
+
+        let mut flee = ai::AiFleeWhenHurtSystem {};
+        flee.run_now(&self.world);
+
+        let mut guard = ai::AiGuardSystem {};
+        guard.run_now(&self.world);
+    }
+
     fn draw_entities(&mut self, context: &mut Rltk) {
         let mut render_system = render::RenderSystem::new(context);
         render_system.run_now(&self.world);
@@ -121,9 +185,16 @@ impl State {
                 _ => {}
             }
         }
+
+        saveload::autosave(&mut self.world);
     }
 
     fn redirect_room(&mut self, original_room: i32, new_room: i32) {
+        // opening a secret is only worth points the first time
+        if !room::has_room_redirection(&self.world, original_room) {
+            self.world.fetch_mut::<score::Score>().secrets += score::SECRET_POINTS;
+        }
+
         // add a redirection then reload the room, no need to adjust the player position
         room::add_room_redirection(&mut self.world, original_room, new_room);
 
@@ -159,43 +230,257 @@ impl State {
             StateAction::RedirectRoom { original_room, new_room } => {
                 self.redirect_room(original_room, new_room);
             }
+            StateAction::TakeItem { item_entity } => {
+                inventory::take_item(&mut self.world, item_entity);
+            }
+            StateAction::DropItem { item } => {
+                inventory::drop_item(&mut self.world, item);
+            }
+            StateAction::GiveItem { item } => {
+                inventory::give_item(&mut self.world, item);
+            }
+            StateAction::EquipItem { item } => {
+                equipment::equip_item(&mut self.world, item);
+            }
+            StateAction::UnequipItem { item } => {
+                equipment::unequip_item(&mut self.world, item);
+            }
+            StateAction::ConsumeItem { item } => {
+                consumables::consume_item(&mut self.world, item);
+            }
+            StateAction::AddRoomFlag { room, flag } => {
+                room::add_room_flag(&mut self.world, room, flag.as_str());
+            }
+            StateAction::UpdateMap { room, x, y, tile } => {
+                room::update_room_tile(&mut self.world, room, x, y, tile);
+            }
+            StateAction::BanishEnemy { room, name } => {
+                room::banish_enemy(&mut self.world, room, name.as_str());
+            }
             StateAction::Victory => {
-                std::process::exit(0);
+                self.run_state = RunState::Victory;
+            }
+            StateAction::PlayerDied { cause } => {
+                self.set_player_description(format!("You have died! You were {}.", cause).as_str());
+                self.run_state = RunState::GameOver {
+                    selected: if saveload::has_any_save_game() {
+                        menu::GameOverOption::LoadLastSave
+                    } else {
+                        menu::GameOverOption::Restart
+                    },
+                };
+            }
+            StateAction::Pause => {
+                self.run_state = RunState::Paused;
             }
             StateAction::Quit => {
-                std::process::exit(0);
+                self.run_state = RunState::Title {
+                    selected: if saveload::has_any_save_game() {
+                        menu::TitleOption::Continue
+                    } else {
+                        menu::TitleOption::NewGame
+                    },
+                };
+            }
+            StateAction::SaveGame { slot } => {
+                self.save_game(slot.as_str());
+            }
+            StateAction::LoadGame { slot } => {
+                self.load_game(slot.as_str());
             }
             StateAction::DebugSave => {
-                self.debug_save();
+                self.save_game(saveload::QUICKSAVE_SLOT);
             }
             StateAction::DebugLoad => {
-                self.debug_load();
+                self.load_game(saveload::QUICKSAVE_SLOT);
             }
             StateAction::None => {}
         }
     }
 
-    fn debug_save(&mut self) {}
+    fn new_game(&mut self) {
+        self.world.delete_all();
+        self.world.maintain();
+
+        self.world.insert(CurrentRoom(0));
+        self.world.insert(PlayTime(0.0));
+        self.world.insert(score::Score::new());
+        random::reset_rng(&mut self.world, random::choose_seed().expect("--seed is checked at startup"));
+        self.world.insert(Vec::<room::RoomRedirection>::new());
+        room::reset_dynamic_rooms(&mut self.world);
+
+        game::create_player_entity(&mut self.world);
+        room::change_room(&mut self.world, 0, -1);
+    }
+
+    fn load_latest_save(&mut self) -> Result<(), saveload::SaveError> {
+        match saveload::list_saves().first() {
+            Some(latest_save) => {
+                let slot = latest_save.slot.clone();
+                saveload::load_game(&mut self.world, slot.as_str())
+            }
+            None => Err(saveload::SaveError::NoSaves),
+        }
+    }
+
+    fn wants_to_pause(&self, context: &Rltk) -> bool {
+        // escape is also used to clear the text input, so only pause when there's nothing to clear
+        if context.key != Some(rltk::VirtualKeyCode::Escape) {
+            return false;
+        }
+        self.world
+            .read_storage::<PlayerTextInputComponent>()
+            .join()
+            .all(|text_input| text_input.get_preview().is_empty())
+    }
 
-    fn debug_load(&mut self) {}
+    fn death_cause(&self) -> String {
+        (&self.world.read_storage::<Player>(), &self.world.read_storage::<DeadTag>())
+            .join()
+            .next()
+            .map(|(_player, dead_tag)| dead_tag.cause.clone())
+            .unwrap_or_default()
+    }
+
+    fn count_treasures(&self) -> (usize, usize) {
+        let mut found = 0;
+        for (_player, inventory) in (
+            &self.world.read_storage::<Player>(),
+            &self.world.read_storage::<InventoryComponent>(),
+        )
+            .join()
+        {
+            found += (inventory.items() & items::ItemFlags::TREASURES).each().len();
+        }
+        (found, items::ItemFlags::TREASURES.each().len())
+    }
+
+    fn save_game(&mut self, slot: &str) {
+        if let Err(error) = saveload::save_game(&mut self.world, slot) {
+            self.set_player_description(error.to_string().as_str());
+        }
+    }
+
+    fn load_game(&mut self, slot: &str) {
+        // the description was saved along with the player, replace it so it's clear the load happened
+        match saveload::load_game(&mut self.world, slot) {
+            Ok(()) => self.set_player_description(format!("loaded {}", slot).as_str()),
+            Err(error) => self.set_player_description(error.to_string().as_str()),
+        }
+    }
+
+    fn set_player_description(&mut self, text: &str) {
+        for (_player, description) in (
+            &self.world.read_storage::<Player>(),
+            &mut self.world.write_storage::<ActiveDescriptionComponent>(),
+        )
+            .join()
+        {
+            description.set(text);
+        }
+    }
 }
 
 impl GameState for State {
     fn tick(&mut self, context: &mut Rltk) {
         context.cls();
-        self.run_systems(context);
+
+        match self.run_state {
+            RunState::Title { selected } => {
+                let can_continue = saveload::has_any_save_game();
+                match menu::title_menu(context, selected, can_continue, self.menu_error.as_deref()) {
+                    menu::MenuResult::NoSelection { selected } => self.run_state = RunState::Title { selected: selected },
+                    menu::MenuResult::Selected { selected } => match selected {
+                        menu::TitleOption::NewGame => {
+                            self.menu_error = None;
+                            self.new_game();
+                            self.run_state = RunState::Playing;
+                        }
+                        menu::TitleOption::Continue => match self.load_latest_save() {
+                            Ok(()) => {
+                                self.menu_error = None;
+                                self.run_state = RunState::Playing;
+                            }
+                            Err(error) => self.menu_error = Some(error.to_string()),
+                        },
+                        menu::TitleOption::Quit => context.quit(),
+                    },
+                }
+            }
+            RunState::Playing => {
+                if self.wants_to_pause(context) {
+                    self.run_state = RunState::Paused;
+                }
+                self.run_systems(context);
+            }
+            RunState::Paused => {
+                self.draw_entities(context);
+                self.draw_hud(context);
+                if menu::pause_overlay(context) {
+                    self.run_state = RunState::Playing;
+                }
+            }
+            RunState::GameOver { selected } => {
+                self.draw_entities(context);
+                self.draw_hud(context);
+
+                let cause = self.death_cause();
+                match menu::game_over_menu(
+                    context,
+                    cause.as_str(),
+                    selected,
+                    saveload::has_any_save_game(),
+                    self.menu_error.as_deref(),
+                ) {
+                    menu::MenuResult::NoSelection { selected } => self.run_state = RunState::GameOver { selected: selected },
+                    menu::MenuResult::Selected { selected } => match selected {
+                        menu::GameOverOption::Restart => {
+                            self.menu_error = None;
+                            self.new_game();
+                            self.run_state = RunState::Playing;
+                        }
+                        menu::GameOverOption::LoadLastSave => match self.load_latest_save() {
+                            Ok(()) => {
+                                self.menu_error = None;
+                                self.run_state = RunState::Playing;
+                            }
+                            Err(error) => self.menu_error = Some(error.to_string()),
+                        },
+                    },
+                }
+            }
+            RunState::Victory => {
+                let (treasures_found, treasures_total) = self.count_treasures();
+                let score = (*self.world.fetch::<score::Score>()).clone();
+                if menu::victory_screen(context, &score, treasures_found, treasures_total) {
+                    self.run_state = RunState::Title {
+                        selected: menu::TitleOption::NewGame,
+                    };
+                }
+            }
+        }
     }
 }
 
 fn main() -> rltk::BError {
+    let seed = random::choose_seed()?;
     let context = terminal_builder(2).build()?;
 
-    let mut game_state = State { world: World::new() };
+    let mut game_state = State {
+        world: World::new(),
+        run_state: RunState::Title {
+            selected: menu::TitleOption::NewGame,
+        },
+        menu_error: None,
+    };
 
     // systems can dump stuff in here to be dealt with
     let pending_actions: Vec<StateAction> = Vec::new();
     game_state.world.insert(pending_actions);
     game_state.world.insert(CurrentRoom(0));
+    game_state.world.insert(PlayTime(0.0));
+    game_state.world.insert(score::Score::new());
+    random::reset_rng(&mut game_state.world, seed);
 
     // register types
     register_markers(&mut game_state.world);
@@ -204,12 +489,12 @@ fn main() -> rltk::BError {
     // load raw data
     items::load_items(&mut game_state.world);
     enemies::load_enemies(&mut game_state.world);
+    textinput::load_text_parser(&mut game_state.world);
+    register_commands(&mut game_state.world);
     room::load_rooms(&mut game_state.world);
     room::load_dynamic_rooms(&mut game_state.world);
 
-    // start game
-    game::create_player_entity(&mut game_state.world);
-    room::change_room(&mut game_state.world, 0, -1);
+    // the game itself gets started from the title menu
     rltk::main_loop(context, game_state)
 }
 
@@ -229,10 +514,22 @@ fn register_components(world: &mut World) {
     world.register::<ColliderComponent>();
     world.register::<room::BelongsToRoom>();
     world.register::<room::ExitTrigger>();
+    world.register::<room::LockedDoor>();
     world.register::<DebugHudComponent>();
+    world.register::<InventoryHudComponent>();
     world.register::<ActiveDescriptionComponent>();
     world.register::<ai::AiMoveToPlayer>();
+    world.register::<ai::AiWander>();
+    world.register::<ai::AiAmbush>();
+    world.register::<ai::AiFleeWhenHurt>();
+    world.register::<ai::AiGuard>();
+    world.register::<visibility::Viewshed>();
     world.register::<InventoryComponent>();
+    world.register::<CarryCapacity>();
+    world.register::<equipment::Equipment>();
+    world.register::<consumables::ActiveBuff>();
+    world.register::<travel::Travel>();
+    world.register::<ConditionalDescription>();
     world.register::<PickupTrigger>();
     world.register::<CombatStats>();
     world.register::<ApplyDamageComponent>();
@@ -242,6 +539,22 @@ fn register_components(world: &mut World) {
     world.register::<DebugName>();
     world.register::<CombatLog>();
     world.register::<Description>();
+    world.register::<score::ScoreValue>();
+}
+
+// each module brings its own text commands, help lists them in this order
+fn register_commands(world: &mut World) {
+    let mut registry = commands::CommandRegistry::new();
+    game::register_commands(&mut registry);
+    travel::register_commands(&mut registry);
+    inventory::register_commands(&mut registry);
+    equipment::register_commands(&mut registry);
+    consumables::register_commands(&mut registry);
+    score::register_commands(&mut registry);
+    saveload::register_commands(&mut registry);
+    debug::register_commands(&mut registry);
+    commands::register_commands(&mut registry);
+    world.insert(registry);
 }
 
 fn terminal_builder(scale: i32) -> rltk::RltkBuilder {
@@ -255,3 +568,180 @@ fn terminal_builder(scale: i32) -> rltk::RltkBuilder {
         .with_title("Castle Adventure!");
     context
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::ItemFlags;
+
+    fn test_world(room: i32) -> World {
+        let mut world = World::new();
+        world.insert(Vec::<StateAction>::new());
+        world.insert(PlayTime(0.0));
+        world.insert(score::Score::new());
+        random::reset_rng(&mut world, 1);
+        register_markers(&mut world);
+        register_components(&mut world);
+        items::load_items(&mut world);
+        enemies::load_enemies(&mut world);
+        textinput::load_text_parser(&mut world);
+        register_commands(&mut world);
+        room::load_rooms(&mut world);
+        room::load_dynamic_rooms(&mut world);
+        world.insert(Vec::<room::RoomRedirection>::new());
+        room::reset_dynamic_rooms(&mut world);
+        game::create_player_entity(&mut world);
+        world.insert(CurrentRoom(room));
+        room::change_room(&mut world, room, -1);
+        world.maintain();
+        world
+    }
+
+    fn command(world: &mut World, text: &str) -> String {
+        for text_input in (&mut world.write_storage::<PlayerTextInputComponent>()).join() {
+            for c in text.chars() {
+                text_input.add_character(c);
+            }
+            text_input.submit();
+        }
+        commands::process_text_commands(world);
+        let active_descriptions = world.read_storage::<ActiveDescriptionComponent>();
+        active_descriptions.join().next().unwrap().description.clone()
+    }
+
+    fn carry(world: &mut World, item: ItemFlags) {
+        for (_player, inventory) in (&world.read_storage::<Player>(), &mut world.write_storage::<InventoryComponent>()).join() {
+            inventory.add(item);
+        }
+    }
+
+    // tries to step from (x, y) by (delta_x, delta_y) without getting anywhere, like walking into something solid
+    fn bump(world: &mut World, x: i32, y: i32, delta_x: i32, delta_y: i32) {
+        for (_player, position, movement) in (
+            &world.read_storage::<Player>(),
+            &mut world.write_storage::<Position>(),
+            &mut world.write_storage::<Movement>(),
+        )
+            .join()
+        {
+            position.x = x;
+            position.y = y;
+            movement.add_movement_input(delta_x, delta_y);
+            movement.clear_movement();
+        }
+        combat::MeleeCombatSystem {}.run_now(world);
+        combat::DamageSystem {}.run_now(world);
+        world.maintain();
+    }
+
+    fn combat_log(world: &World) -> Vec<String> {
+        let combat_logs = world.read_storage::<CombatLog>();
+        combat_logs.join().next().unwrap().logs.clone()
+    }
+
+    fn next_roll(world: &World) -> i32 {
+        world.fetch_mut::<rltk::RandomNumberGenerator>().roll_dice(1, 1_000_000)
+    }
+
+    // walks out to another room and straight back in, so the room is rebuilt from its saved state
+    fn revisit(world: &mut World, room: i32) {
+        let other_room = if room == 0 { 1 } else { 0 };
+        world.insert(CurrentRoom(other_room));
+        room::change_room(world, other_room, room);
+        world.insert(CurrentRoom(room));
+        room::change_room(world, room, other_room);
+        world.maintain();
+    }
+
+    #[test]
+    fn the_book_can_still_be_read_after_coming_back_to_its_room() {
+        let mut world = test_world(57);
+        carry(&mut world, ItemFlags::EYEGLASSES);
+        let readable = command(&mut world, "look book");
+        assert!(!readable.contains("Blurry"), "{}", readable);
+
+        revisit(&mut world, 57);
+        revisit(&mut world, 57);
+        assert_eq!(command(&mut world, "look book"), readable);
+    }
+
+    #[test]
+    fn a_banished_vampire_leaves_nothing_to_look_at() {
+        let mut world = test_world(27);
+        revisit(&mut world, 27);
+        room::banish_enemy(&mut world, 27, "vampire");
+        assert_eq!(command(&mut world, "look vampire"), "you don't see a vampire here");
+
+        revisit(&mut world, 27);
+        assert_eq!(command(&mut world, "look vampire"), "you don't see a vampire here");
+    }
+
+    #[test]
+    fn a_dropped_item_comes_back_once() {
+        let mut world = test_world(57);
+        carry(&mut world, ItemFlags::EYEGLASSES);
+        inventory::drop_item(&mut world, ItemFlags::EYEGLASSES);
+        revisit(&mut world, 57);
+        revisit(&mut world, 57);
+
+        let descriptions = world.read_storage::<Description>();
+        let glasses = descriptions
+            .join()
+            .filter(|description| description.input_name == "glasses")
+            .count();
+        assert_eq!(glasses, 1);
+    }
+
+    #[test]
+    fn bumping_a_wall_is_not_an_attack() {
+        let mut world = test_world(57);
+        let untouched_world = test_world(57);
+        let log = combat_log(&world);
+
+        bump(&mut world, 5, 5, -1, 0);
+        assert_eq!(combat_log(&world), log);
+        assert_eq!(next_roll(&world), next_roll(&untouched_world));
+    }
+
+    #[test]
+    fn bumping_a_locked_door_only_says_it_is_locked() {
+        let mut world = test_world(67);
+        let untouched_world = test_world(67);
+        let log = combat_log(&world);
+
+        bump(&mut world, 8, 13, 0, 1);
+        game::LockedDoorSystem::new().run_now(&world);
+        let active_descriptions = world.read_storage::<ActiveDescriptionComponent>();
+        assert_eq!(active_descriptions.join().next().unwrap().description, "The door is locked");
+        assert_eq!(combat_log(&world), log);
+        assert_eq!(next_roll(&world), next_roll(&untouched_world));
+    }
+
+    #[test]
+    fn the_dark_passage_needs_the_lamp() {
+        let mut world = test_world(68);
+        assert_eq!(command(&mut world, "look"), "It is too dark to see");
+
+        carry(&mut world, ItemFlags::LAMP);
+        assert_ne!(command(&mut world, "look"), "It is too dark to see");
+    }
+
+    #[test]
+    fn looking_around_starts_with_the_long_description() {
+        let mut world = test_world(27);
+        let look = command(&mut world, "look");
+        assert!(look.starts_with("The corridor is cold and smells of damp earth."), "{}", look);
+    }
+
+    #[test]
+    fn the_wand_only_opens_the_passage_once() {
+        let mut world = test_world(66);
+        carry(&mut world, ItemFlags::MAGICWAND);
+        room::add_room_flag(&mut world, 66, "passage_open");
+        assert_eq!(command(&mut world, "wave wand"), "nothing happens");
+
+        let mut world = test_world(66);
+        carry(&mut world, ItemFlags::MAGICWAND);
+        assert_eq!(command(&mut world, "wave wand"), "a secret passage opens!");
+    }
+}
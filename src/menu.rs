@@ -0,0 +1,156 @@
+use rltk::{Rltk, VirtualKeyCode, RGB};
+
+use crate::score::Score;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum TitleOption {
+    NewGame,
+    Continue,
+    Quit,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum GameOverOption {
+    Restart,
+    LoadLastSave,
+}
+
+pub enum MenuResult<T> {
+    NoSelection { selected: T },
+    Selected { selected: T },
+}
+
+const TITLE_OPTIONS: [(TitleOption, &str); 3] = [
+    (TitleOption::NewGame, "New Game"),
+    (TitleOption::Continue, "Continue"),
+    (TitleOption::Quit, "Quit"),
+];
+
+const GAME_OVER_OPTIONS: [(GameOverOption, &str); 2] = [
+    (GameOverOption::Restart, "Restart"),
+    (GameOverOption::LoadLastSave, "Load Last Save"),
+];
+
+pub fn title_menu(context: &mut Rltk, selected: TitleOption, can_continue: bool, error: Option<&str>) -> MenuResult<TitleOption> {
+    context.print_color_centered(6, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Castle Adventure!");
+
+    let is_enabled = |option: TitleOption| option != TitleOption::Continue || can_continue;
+    draw_options(context, 11, &TITLE_OPTIONS, selected, &is_enabled);
+    draw_error(context, 18, error);
+    select_option(context, &TITLE_OPTIONS, selected, &is_enabled)
+}
+
+pub fn game_over_menu(
+    context: &mut Rltk,
+    cause: &str,
+    selected: GameOverOption,
+    can_load: bool,
+    error: Option<&str>,
+) -> MenuResult<GameOverOption> {
+    context.draw_box(7, 5, 25, 12, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    context.print_color_centered(7, RGB::named(rltk::RED), RGB::named(rltk::BLACK), "You Have Died!");
+    context.print_centered(9, cause);
+
+    let is_enabled = |option: GameOverOption| option != GameOverOption::LoadLastSave || can_load;
+    draw_options(context, 12, &GAME_OVER_OPTIONS, selected, &is_enabled);
+    draw_error(context, 19, error);
+    select_option(context, &GAME_OVER_OPTIONS, selected, &is_enabled)
+}
+
+// ie. why the last save wouldn't load, wrapped to fit the screen
+fn draw_error(context: &mut Rltk, start_y: i32, error: Option<&str>) {
+    let error = match error {
+        Some(error) => error,
+        None => return,
+    };
+
+    let mut lines: Vec<String> = vec![String::new()];
+    for token in error.split_whitespace() {
+        let current_line = lines.last_mut().unwrap();
+        if !current_line.is_empty() && current_line.len() + token.len() + 1 > 36 {
+            lines.push(token.to_string());
+            continue;
+        }
+
+        if !current_line.is_empty() {
+            current_line.push(' ');
+        }
+        current_line.push_str(token);
+    }
+
+    for (row, line) in lines.iter().enumerate() {
+        context.print_color_centered(start_y + row as i32, RGB::named(rltk::RED), RGB::named(rltk::BLACK), line);
+    }
+}
+
+fn draw_options<T: Copy + PartialEq>(context: &mut Rltk, start_y: i32, options: &[(T, &str)], selected: T, is_enabled: &dyn Fn(T) -> bool) {
+    let mut y = start_y;
+    for (option, text) in options.iter() {
+        let color = if *option == selected {
+            rltk::MAGENTA
+        } else if !is_enabled(*option) {
+            rltk::DARK_GREY
+        } else {
+            rltk::WHITE
+        };
+        context.print_color_centered(y, RGB::named(color), RGB::named(rltk::BLACK), text);
+        y += 2;
+    }
+}
+
+fn select_option<T: Copy + PartialEq>(context: &Rltk, options: &[(T, &str)], selected: T, is_enabled: &dyn Fn(T) -> bool) -> MenuResult<T> {
+    match context.key {
+        Some(VirtualKeyCode::Up) => MenuResult::NoSelection {
+            selected: next_option(options, selected, -1, is_enabled),
+        },
+        Some(VirtualKeyCode::Down) => MenuResult::NoSelection {
+            selected: next_option(options, selected, 1, is_enabled),
+        },
+        Some(VirtualKeyCode::Return) if is_enabled(selected) => MenuResult::Selected { selected: selected },
+        _ => MenuResult::NoSelection { selected: selected },
+    }
+}
+
+fn next_option<T: Copy + PartialEq>(options: &[(T, &str)], selected: T, direction: i32, is_enabled: &dyn Fn(T) -> bool) -> T {
+    let count = options.len() as i32;
+    let mut index = options.iter().position(|(option, _)| *option == selected).unwrap_or(0) as i32;
+    for _ in 0..count {
+        index = (index + direction + count) % count;
+        let option = options[index as usize].0;
+        if is_enabled(option) {
+            return option;
+        }
+    }
+    selected
+}
+
+fn draw_overlay_box(context: &mut Rltk, lines: &[String]) {
+    let width = lines.iter().map(|line| line.len()).max().unwrap_or(0) as i32 + 4;
+    let height = lines.len() as i32 + 3;
+    let x = (40 - width) / 2;
+    let y = (25 - height) / 2;
+    context.draw_box(x, y, width - 1, height - 1, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    for (row, line) in lines.iter().enumerate() {
+        context.print_centered(y + 1 + row as i32, line);
+    }
+}
+
+pub fn pause_overlay(context: &mut Rltk) -> bool {
+    draw_overlay_box(context, &["Paused".to_string(), "".to_string(), "Press Enter".to_string()]);
+    context.key == Some(VirtualKeyCode::Return) || context.key == Some(VirtualKeyCode::Escape)
+}
+
+pub fn victory_screen(context: &mut Rltk, score: &Score, treasures_found: usize, treasures_total: usize) -> bool {
+    context.print_color_centered(6, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "You Win!");
+    context.print_centered(9, "You escaped the castle");
+    context.print_centered(11, format!("Final Score: {}", score.total()));
+    context.print_centered(12, format!("Treasures: {} of {}", treasures_found, treasures_total));
+    context.print_color_centered(
+        14,
+        RGB::named(rltk::MAGENTA),
+        RGB::named(rltk::BLACK),
+        format!("Rank: {}", score.rank()),
+    );
+    context.print_centered(16, "Press Enter");
+    context.key == Some(VirtualKeyCode::Return)
+}
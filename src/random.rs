@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// the seed the current game's rng started from, play it again with --seed <seed> to get the same rolls
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct GameSeed(pub u64);
+
+// the seed given on the command line, or one from the clock
+pub fn choose_seed() -> Result<u64, String> {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--seed") {
+        return match args.get(index + 1) {
+            Some(seed) => seed.parse::<u64>().map_err(|_| format!("--seed needs a number, not {}", seed)),
+            None => Err("--seed needs a number".to_string()),
+        };
+    }
+
+    return Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or(0));
+}
+
+// everything random in the game draws from the one rng resource, so it has to be reset along with the seed
+pub fn reset_rng(world: &mut World, seed: u64) {
+    world.insert(GameSeed(seed));
+    world.insert(rltk::RandomNumberGenerator::seeded(seed));
+}
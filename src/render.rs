@@ -2,11 +2,13 @@ use rltk::RGB;
 use serde::{Deserialize, Serialize};
 use specs::error::NoError;
 use specs::prelude::*;
-use specs::saveload::{ConvertSaveload, Marker};
+use specs::saveload::{ConvertSaveload, Marker, SimpleMarker};
 use specs_derive::Component;
 use specs_derive::ConvertSaveload;
 
-use crate::components::Position;
+use crate::components::{Player, Position};
+use crate::game::DynamicMarker;
+use crate::visibility::{can_player_remember, can_player_see, Viewshed};
 
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Renderable {
@@ -31,6 +33,9 @@ impl Renderable {
     }
 }
 
+// how bright remembered tiles are compared to ones in view
+const REMEMBERED_DIM: f32 = 0.4;
+
 pub struct RenderSystem<'a> {
     context: &'a mut rltk::Rltk,
 }
@@ -42,21 +47,31 @@ impl<'a> RenderSystem<'a> {
 }
 
 impl<'a> System<'a> for RenderSystem<'_> {
-    type SystemData = (ReadStorage<'a, Position>, ReadStorage<'a, Renderable>);
-
-    fn run(&mut self, (positions, renderables): Self::SystemData) {
-        for zorder in 0..2 {
-            for m in (&positions, &renderables).join().filter(|a| a.1.zorder == zorder) {
-                let position = &m.0;
-                let renderable = &m.1;
-
-                self.context.set(
-                    position.x,
-                    position.y,
-                    renderable.color,
-                    rltk::RGB::named(rltk::BLACK),
-                    renderable.glyph,
-                );
+    type SystemData = (
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Viewshed>,
+        ReadStorage<'a, SimpleMarker<DynamicMarker>>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Renderable>,
+    );
+
+    fn run(&mut self, (players, viewsheds, dynamic_markers, positions, renderables): Self::SystemData) {
+        for zorder in 0..3 {
+            for (position, renderable, dynamic_marker) in (&positions, &renderables, dynamic_markers.maybe())
+                .join()
+                .filter(|a| a.1.zorder == zorder)
+            {
+                // out of sight things are hidden, but the walls of the room (anything unmarked) are remembered
+                let color = if can_player_see(&players, &viewsheds, position) {
+                    renderable.color
+                } else if dynamic_marker.is_none() && can_player_remember(&players, &viewsheds, position) {
+                    renderable.color * REMEMBERED_DIM
+                } else {
+                    continue;
+                };
+
+                self.context
+                    .set(position.x, position.y, color, rltk::RGB::named(rltk::BLACK), renderable.glyph);
             }
         }
     }
@@ -33,6 +33,25 @@ pub struct DynamicEnemy {
     pub health: Option<i32>, // Option so that when specifying the initial level layout I don't need to specify health, but I do want it saved as state
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DynamicUsableData {
+    pub key: String,                   // the verb, ie. use, wave
+    pub text: String,                  // what it's used on (or with)
+    pub requires: Option<String>,      // item the player has to be carrying
+    pub target: Option<String>,        // who it's for, ie. the fairy in "give goblet to fairy"
+    pub requires_flag: Option<String>, // room flag that has to be set first
+    pub unless_flag: Option<String>,   // room flag that stops it working, ie. once it's already been done
+    pub description: String,
+    pub actions: Vec<DynamicActionData>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DynamicTileChange {
+    pub x: i32,
+    pub y: i32,
+    pub tile: u8,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DynamicRoomData {
     pub room: i32,
@@ -40,6 +59,26 @@ pub struct DynamicRoomData {
     pub descriptions: Vec<DynamicDescriptionData>,
     pub enemies: Vec<DynamicEnemy>,
     pub map: Option<DynamicMapData>,
+    #[serde(default)]
+    pub flags: Vec<String>,
+    #[serde(default)]
+    pub tile_changes: Vec<DynamicTileChange>,
+    #[serde(default)]
+    pub banished: Vec<String>, // enemies that are gone for good
+}
+
+// an entry in dynrooms.json, the room's starting state plus the parts of it that never change. only the state
+// goes in the saves, the rest is loaded into the room data
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct DynamicRoomFileData {
+    #[serde(flatten)]
+    state: DynamicRoomData,
+    #[serde(default)]
+    dark: bool, // needs a light to see anything
+    #[serde(default)]
+    long_description: Option<String>, // shown by a plain look
+    #[serde(default)]
+    usables: Vec<DynamicUsableData>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -53,7 +92,10 @@ pub struct DynamicMapData {
 pub enum DynamicActionData {
     GiveItem { item: String },
     AddFlag { flag: String },
-    UpdateMap { x: i32, y: i32 },
+    UpdateMap { x: i32, y: i32, tile: Option<u8> }, // no tile clears it
+    BanishEnemy { name: String },
+    RedirectRoom { new_room: i32 },
+    Victory,
 }
 
 impl DynamicRoomData {
@@ -64,56 +106,84 @@ impl DynamicRoomData {
             descriptions: Vec::new(),
             enemies: Vec::new(),
             map: None,
+            flags: Vec::new(),
+            tile_changes: Vec::new(),
+            banished: Vec::new(),
         }
     }
+
+    pub fn is_banished(&self, enemy_name: &str) -> bool {
+        self.banished.iter().any(|banished| banished == enemy_name)
+    }
+
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|room_flag| room_flag == flag)
+    }
 }
 
 pub fn load_dynamic_rooms(world: &mut World) {
     setup_dynamic_room_data_example();
 
-    let f = File::open("data/dynrooms.json").expect("data not found");
-    let loaded_rooms: Vec<DynamicRoomData> = serde_json::from_reader(f).expect("failed to deserializer!");
+    let loaded_rooms = read_dynamic_rooms();
 
-    let mut rooms = Vec::new();
     // 83 + 2 extra = 85
     for room in 0..85 {
-        match find_room(room, &loaded_rooms) {
-            Some(dyn_room_data) => {
-                rooms.push(dyn_room_data.clone());
-                match &(dyn_room_data.map) {
-                    Some(map_data) => {
-                        let mut existing_rooms = world.fetch_mut::<Vec<crate::room::RoomData>>();
-                        let mut dyn_room = crate::room::RoomData::new();
-
-                        for row in 0..18 {
-                            for col in 0..24 {
-                                let t = map_data.map[(row * 24) + col];
-
-                                // blank tile, skip it
-                                if t == 32 {
-                                    continue;
-                                };
-
-                                let mut tile_data = crate::room::get_tile_data_from_ascii_char(t);
-                                tile_data.x = col as i32;
-                                tile_data.y = row as i32;
-                                dyn_room.tiles.push(tile_data);
-                            }
-                        }
-
-                        for desc_line in &map_data.description {
-                            dyn_room.description.push((*desc_line).clone());
+        if let Some(room_file_data) = find_room(room, &loaded_rooms) {
+            match &(room_file_data.state.map) {
+                Some(map_data) => {
+                    let mut existing_rooms = world.fetch_mut::<Vec<crate::room::RoomData>>();
+                    let mut dyn_room = crate::room::RoomData::new();
+
+                    for row in 0..18 {
+                        for col in 0..24 {
+                            let t = map_data.map[(row * 24) + col];
+
+                            // blank tile, skip it
+                            if t == 32 {
+                                continue;
+                            };
+
+                            let mut tile_data = crate::room::get_tile_data_from_ascii_char(t);
+                            tile_data.x = col as i32;
+                            tile_data.y = row as i32;
+                            dyn_room.tiles.push(tile_data);
                         }
+                    }
 
-                        for exit in crate::room::parse_exits(map_data.exits.as_str()) {
-                            dyn_room.exits.push(exit);
-                        }
+                    for desc_line in &map_data.description {
+                        dyn_room.description.push((*desc_line).clone());
+                    }
 
-                        existing_rooms.push(dyn_room);
+                    for exit in crate::room::parse_exits(map_data.exits.as_str()) {
+                        dyn_room.exits.push(exit);
                     }
-                    None => {}
+
+                    existing_rooms.push(dyn_room);
                 }
+                None => {}
+            }
+
+            // darkness, the long description and the usables never change, so they live with the rest of the room data
+            let mut existing_rooms = world.fetch_mut::<Vec<crate::room::RoomData>>();
+            if room_file_data.dark {
+                existing_rooms[room as usize].dark = true;
             }
+            existing_rooms[room as usize].long_description = room_file_data.long_description.clone();
+            existing_rooms[room as usize].usables = room_file_data.usables.clone();
+        }
+    }
+
+    reset_dynamic_rooms(world);
+}
+
+// puts every room back the way it was at the start of the game
+pub fn reset_dynamic_rooms(world: &mut World) {
+    let loaded_rooms = read_dynamic_rooms();
+
+    let mut rooms = Vec::new();
+    for room in 0..85 {
+        match find_room(room, &loaded_rooms) {
+            Some(room_file_data) => rooms.push(room_file_data.state.clone()),
             None => rooms.push(DynamicRoomData::empty(room)),
         }
     }
@@ -121,8 +191,17 @@ pub fn load_dynamic_rooms(world: &mut World) {
     world.insert(rooms);
 }
 
+fn read_dynamic_rooms() -> Vec<DynamicRoomFileData> {
+    let f = File::open("data/dynrooms.json").expect("data not found");
+    serde_json::from_reader(f).expect("failed to deserializer!")
+}
+
 pub fn update_dynamic_room(world: &mut World, room: i32) {
-    let mut room_data = DynamicRoomData::empty(room);
+    // only the entities get rebuilt, anything else (flags, tile changes, etc.) carries over
+    let mut room_data = get_dynamic_room_data(world, room);
+    room_data.items.clear();
+    room_data.descriptions.clear();
+    room_data.enemies.clear();
 
     let room_ownership = world.read_storage::<BelongsToRoom>();
     let positions = world.read_storage::<Position>();
@@ -137,8 +216,10 @@ pub fn update_dynamic_room(world: &mut World, room: i32) {
         room_data.items.push(i);
     }
 
+    // items and enemies bring their own descriptions when they're rebuilt, so only keep the standalone ones
     let descriptions = world.read_storage::<Description>();
-    for (desc, _mark, _room) in (&descriptions, &marked, &room_ownership).join() {
+    let combat_stats = world.read_storage::<CombatStats>();
+    for (desc, _mark, _room, _not_item, _not_enemy) in (&descriptions, &marked, &room_ownership, !&pickups, !&combat_stats).join() {
         let d = DynamicDescriptionData {
             keyword: desc.input_name.clone(),
             text: desc.description.clone(),
@@ -146,7 +227,6 @@ pub fn update_dynamic_room(world: &mut World, room: i32) {
         room_data.descriptions.push(d);
     }
 
-    let combat_stats = world.read_storage::<CombatStats>();
     for (combat_stat, description, position, _room) in (&combat_stats, &descriptions, &positions, &room_ownership).join() {
         let e = DynamicEnemy {
             name: description.input_name.clone(),
@@ -164,16 +244,16 @@ pub fn update_dynamic_room(world: &mut World, room: i32) {
     room_datas[room as usize] = room_data;
 }
 
-fn find_room<'a>(room_index: i32, rooms: &'a Vec<DynamicRoomData>) -> Option<&'a DynamicRoomData> {
+fn find_room<'a>(room_index: i32, rooms: &'a Vec<DynamicRoomFileData>) -> Option<&'a DynamicRoomFileData> {
     for room in rooms {
-        if room.room == room_index {
+        if room.state.room == room_index {
             return Some(&room);
         }
     }
     return None;
 }
 
-fn get_dynamic_room_data(world: &World, room: i32) -> DynamicRoomData {
+pub fn get_dynamic_room_data(world: &World, room: i32) -> DynamicRoomData {
     let room_datas = world.fetch::<Vec<DynamicRoomData>>();
     room_datas[room as usize].clone()
 }
@@ -192,7 +272,7 @@ pub fn create_dynamic_room_entities(world: &mut World, room: i32) {
         create_description(world, room, keyword, description)
     }
 
-    for enemy in &room_data.enemies {
+    for enemy in room_data.enemies.iter().filter(|enemy| !room_data.is_banished(enemy.name.as_str())) {
         let enemy_name = enemy.name.as_str();
         let health = enemy.health;
         crate::enemies::create_enemy(world, room, enemy_name, enemy.position.x, enemy.position.y, health);
@@ -230,12 +310,20 @@ fn setup_dynamic_room_data_example() {
         }
     }
 
-    let room = DynamicRoomData {
-        room: 1,
-        items: Vec::new(),
-        descriptions: Vec::new(),
-        enemies: Vec::new(),
-        map: Some(map),
+    let room = DynamicRoomFileData {
+        state: DynamicRoomData {
+            room: 1,
+            items: Vec::new(),
+            descriptions: Vec::new(),
+            enemies: Vec::new(),
+            map: Some(map),
+            flags: Vec::new(),
+            tile_changes: Vec::new(),
+            banished: Vec::new(),
+        },
+        dark: false,
+        long_description: Some("A long description for a plain look".to_string()),
+        usables: Vec::new(),
     };
     rooms.push(room);
 
@@ -244,3 +332,46 @@ fn setup_dynamic_room_data_example() {
 
     (&rooms).serialize(&mut serializer).expect("failed to save example rooms");
 }
+
+pub fn add_room_flag(world: &mut World, room: i32, flag: &str) {
+    let mut room_datas = world.fetch_mut::<Vec<DynamicRoomData>>();
+    let room_data = &mut room_datas[room as usize];
+    if !room_data.has_flag(flag) {
+        room_data.flags.push(flag.to_string());
+    }
+}
+
+// remembers the new tile so it's still there next time the room is built
+pub fn add_tile_change(world: &mut World, room: i32, x: i32, y: i32, tile: u8) {
+    let mut room_datas = world.fetch_mut::<Vec<DynamicRoomData>>();
+    let tile_changes = &mut room_datas[room as usize].tile_changes;
+    tile_changes.retain(|tile_change| tile_change.x != x || tile_change.y != y);
+    tile_changes.push(DynamicTileChange { x: x, y: y, tile: tile });
+}
+
+// removes an enemy from the room and makes sure it never comes back
+pub fn banish_enemy(world: &mut World, room: i32, enemy_name: &str) {
+    let banished_entities: Vec<Entity> = {
+        let entities = world.entities();
+        let room_ownership = world.read_storage::<BelongsToRoom>();
+        let combat_stats = world.read_storage::<CombatStats>();
+        let descriptions = world.read_storage::<Description>();
+        (&entities, &room_ownership, &combat_stats, &descriptions)
+            .join()
+            .filter(|(_entity, belongs_to_room, _combat_stats, description)| {
+                belongs_to_room.room == room && description.input_name == enemy_name
+            })
+            .map(|(entity, _, _, _)| entity)
+            .collect()
+    };
+    world
+        .delete_entities(&banished_entities)
+        .expect("failed to delete banished enemies");
+
+    let mut room_datas = world.fetch_mut::<Vec<DynamicRoomData>>();
+    let room_data = &mut room_datas[room as usize];
+    room_data.enemies.retain(|enemy| enemy.name != enemy_name);
+    if !room_data.is_banished(enemy_name) {
+        room_data.banished.push(enemy_name.to_string());
+    }
+}
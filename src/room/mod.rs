@@ -2,17 +2,20 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 use specs::error::NoError;
 use specs::prelude::*;
-use specs::saveload::{ConvertSaveload, Marker};
+use specs::saveload::{ConvertSaveload, Marker, SimpleMarker};
 use specs_derive::{Component, ConvertSaveload};
 use std::fs::File;
 use std::io::Read;
 use std::str;
 
-use crate::components::{ColliderComponent, Position};
+use crate::components::{ColliderComponent, Condition, InventoryComponent, Position};
+use crate::items::ItemFlags;
 use crate::render::Renderable;
 
 pub mod dynamic_rooms;
+pub mod room_map;
 pub use dynamic_rooms::*;
+pub use room_map::*;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum ExitDirection {
@@ -26,6 +29,34 @@ pub enum ExitDirection {
     Down,
 }
 
+impl ExitDirection {
+    pub fn name(&self) -> &str {
+        match self {
+            ExitDirection::Invalid => "nowhere",
+            ExitDirection::North => "north",
+            ExitDirection::South => "south",
+            ExitDirection::East => "east",
+            ExitDirection::West => "west",
+            ExitDirection::Up => "up",
+            ExitDirection::Down => "down",
+        }
+    }
+
+    // "north" or just "n"
+    pub fn from_name(name: &str) -> Option<ExitDirection> {
+        let direction = match name {
+            "n" | "north" => ExitDirection::North,
+            "s" | "south" => ExitDirection::South,
+            "e" | "east" => ExitDirection::East,
+            "w" | "west" => ExitDirection::West,
+            "u" | "up" => ExitDirection::Up,
+            "d" | "down" => ExitDirection::Down,
+            _ => return None,
+        };
+        Some(direction)
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Collision {
     Enabled,
@@ -51,6 +82,9 @@ pub struct RoomData {
     tiles: Vec<TileData>,
     pub description: Vec<String>,
     exits: Vec<ExitData>,
+    pub dark: bool,
+    pub long_description: Option<String>, // from dynrooms.json, most rooms don't have one
+    pub usables: Vec<DynamicUsableData>,  // also from dynrooms.json
 }
 
 impl Clone for RoomData {
@@ -65,6 +99,9 @@ impl Clone for RoomData {
         for exit_data in &self.exits {
             new.exits.push(*exit_data);
         }
+        new.dark = self.dark;
+        new.long_description = self.long_description.clone();
+        new.usables = self.usables.clone();
         return new;
     }
 }
@@ -75,7 +112,71 @@ impl RoomData {
             tiles: Vec::new(),
             description: Vec::new(),
             exits: Vec::new(),
+            dark: false,
+            long_description: None,
+            usables: Vec::new(),
+        }
+    }
+
+    pub fn find_usables<'a>(&'a self, text: &'a str) -> impl Iterator<Item = &'a DynamicUsableData> + 'a {
+        self.usables.iter().filter(move |usable| usable.text == text)
+    }
+
+    // the lamp is the only light in the castle
+    pub fn is_too_dark(&self, inventory: Option<&InventoryComponent>) -> bool {
+        let light = Condition { requires: ItemFlags::LAMP };
+        match inventory {
+            Some(inventory) => self.dark && !light.is_met(inventory),
+            None => self.dark,
+        }
+    }
+
+    // rooms without a long description fall back on the five lines from castle.ran
+    pub fn get_long_description(&self) -> String {
+        match &self.long_description {
+            Some(long_description) => long_description.clone(),
+            None => self
+                .description
+                .iter()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<&str>>()
+                .join(" "),
+        }
+    }
+
+    // castle.ran doesn't name the rooms, but the description always starts with "You are in the <name>."
+    pub fn get_name(&self) -> String {
+        let text = self.description.iter().map(|line| line.trim()).collect::<Vec<&str>>().join(" ");
+        let first_sentence = text.split('.').next().unwrap_or("").to_ascii_lowercase();
+        let words: Vec<&str> = first_sentence.split_whitespace().collect();
+
+        let mut start = 0;
+        if words.len() > 2 && words[0] == "you" && words[1] == "are" {
+            start = if ["in", "on", "at"].contains(&words[2]) { 3 } else { 2 };
+        }
+        if words.get(start).is_some_and(|word| ["the", "a", "an"].contains(word)) {
+            start += 1;
+        }
+        words[usize::min(start, words.len())..].join(" ")
+    }
+
+    pub fn get_exit_directions(&self) -> Vec<ExitDirection> {
+        self.exits.iter().map(|exit_data| exit_data.direction).collect()
+    }
+
+    fn set_tile(&mut self, x: i32, y: i32, ascii_char: u8) {
+        self.tiles.retain(|tile| tile.x != x || tile.y != y);
+
+        // blank tile, nothing to add
+        if ascii_char == 32 {
+            return;
         }
+
+        let mut tile_data = get_tile_data_from_ascii_char(ascii_char);
+        tile_data.x = x;
+        tile_data.y = y;
+        self.tiles.push(tile_data);
     }
 }
 
@@ -84,6 +185,12 @@ pub struct BelongsToRoom {
     pub room: i32,
 }
 
+// a door tile that needs the key, opened doors are stored as tile changes in the dynamic room data
+#[derive(Component, Debug)]
+pub struct LockedDoor {}
+
+pub const LOCKED_DOOR_TILE: u8 = 240;
+
 #[derive(Component, Debug)]
 pub struct ExitTrigger {
     pub from_direction: ExitDirection,
@@ -99,6 +206,7 @@ impl ExitTrigger {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct RoomRedirection {
     original_room: i32,
     new_room: i32,
@@ -116,14 +224,35 @@ pub fn change_room(world: &mut World, new_room: i32, old_room: i32) {
         world.delete_entity(old_entity).expect("Unable to delete entity");
     }
 
-    // set up the new room; we only use the new room for the map data; all dynamic stuff
-    // continues to use the actual room
-    let redirected_room = find_redirected_room(&world.fetch::<Vec<RoomRedirection>>(), new_room);
-    let room_data = get_room_data(world, redirected_room);
-    create_room_entities(world, new_room, &room_data);
+    // set up the new room
+    create_room_map_entities(world, new_room);
     create_dynamic_room_entities(world, new_room);
 }
 
+pub fn create_room_map_entities(world: &mut World, room: i32) {
+    // we only use the redirected room for the map data; all dynamic stuff
+    // continues to use the actual room
+    let redirected_room = find_redirected_room(&world.fetch::<Vec<RoomRedirection>>(), room);
+    let mut room_data = get_room_data(world, redirected_room);
+    for tile_change in &get_dynamic_room_data(world, room).tile_changes {
+        room_data.set_tile(tile_change.x, tile_change.y, tile_change.tile);
+    }
+    create_room_entities(world, room, &room_data);
+}
+
+pub fn update_room_tile(world: &mut World, room: i32, x: i32, y: i32, tile: u8) {
+    add_tile_change(world, room, x, y, tile);
+
+    // rebuild the map if we're in the room, the dynamic entities stay as they are
+    if world.fetch::<crate::game::CurrentRoom>().0 == room {
+        let map_entities = find_map_entities(world, room);
+        for map_entity in map_entities {
+            world.delete_entity(map_entity).expect("Unable to delete entity");
+        }
+        create_room_map_entities(world, room);
+    }
+}
+
 fn find_redirected_room(room_redirections: &Vec<RoomRedirection>, room: i32) -> i32 {
     for room_redirection in room_redirections {
         if room_redirection.original_room == room {
@@ -133,6 +262,59 @@ fn find_redirected_room(room_redirections: &Vec<RoomRedirection>, room: i32) ->
     return room;
 }
 
+// the room data a room is showing, which is the redirected room's once a secret has been opened
+pub fn get_redirected_room_data(world: &World, room: i32) -> RoomData {
+    let redirected_room = find_redirected_room(&world.fetch::<Vec<RoomRedirection>>(), room);
+    get_room_data(world, redirected_room)
+}
+
+// the exits out of a room, which come from the redirected room's map same as the exit triggers do
+pub fn get_room_exits(world: &World, room: i32) -> Vec<ExitData> {
+    let redirected_room = find_redirected_room(&world.fetch::<Vec<RoomRedirection>>(), room);
+    world.fetch::<Vec<RoomData>>()[redirected_room as usize].exits.clone()
+}
+
+// the shortest run of exits from one room to the nearest room that passes the check, along with that room
+pub fn find_route(world: &World, from_room: i32, is_destination: impl Fn(i32) -> bool) -> Option<(i32, Vec<ExitData>)> {
+    let room_count = world.fetch::<Vec<RoomData>>().len();
+    let mut came_from: Vec<Option<(i32, ExitData)>> = vec![None; room_count];
+    let mut visited = vec![false; room_count];
+    let mut queue = std::collections::VecDeque::new();
+    visited[from_room as usize] = true;
+    queue.push_back(from_room);
+
+    while let Some(room) = queue.pop_front() {
+        if room != from_room && is_destination(room) {
+            let mut route = Vec::new();
+            let mut current = room;
+            while let Some((previous_room, exit_data)) = came_from[current as usize] {
+                route.push(exit_data);
+                current = previous_room;
+            }
+            route.reverse();
+            return Some((room, route));
+        }
+
+        for exit_data in get_room_exits(world, room) {
+            let to_room = exit_data.to_room;
+            if to_room < 0 || to_room as usize >= room_count || visited[to_room as usize] {
+                continue;
+            }
+            visited[to_room as usize] = true;
+            came_from[to_room as usize] = Some((room, exit_data));
+            queue.push_back(to_room);
+        }
+    }
+    None
+}
+
+pub fn has_room_redirection(world: &World, original_room: i32) -> bool {
+    let room_redirections = world.fetch::<Vec<RoomRedirection>>();
+    room_redirections
+        .iter()
+        .any(|room_redirection| room_redirection.original_room == original_room)
+}
+
 pub fn add_room_redirection(world: &mut World, original_room: i32, new_room: i32) {
     if let Some(room_redirections) = world.get_mut::<Vec<RoomRedirection>>() {
         room_redirections.push(RoomRedirection {
@@ -159,6 +341,18 @@ fn find_entities_to_remove(world: &mut World, old_room: i32) -> Vec<Entity> {
     entities_to_delete
 }
 
+fn find_map_entities(world: &mut World, room: i32) -> Vec<Entity> {
+    let entities = world.entities();
+    let room_ownership = world.read_storage::<BelongsToRoom>();
+    let marked = world.read_storage::<SimpleMarker<crate::game::DynamicMarker>>();
+
+    (&entities, &room_ownership, !&marked)
+        .join()
+        .filter(|(_entity, belongs_to_room, _)| belongs_to_room.room == room)
+        .map(|(entity, _, _)| entity)
+        .collect()
+}
+
 pub fn get_room_data(world: &World, room: i32) -> RoomData {
     let room_datas = world.fetch::<Vec<RoomData>>();
     room_datas[room as usize].clone()
@@ -175,6 +369,10 @@ pub fn create_room_entities(world: &mut World, room: i32, room_data: &RoomData)
             entity_builder = entity_builder.with(ColliderComponent {});
         }
 
+        if tile.glyph == map_ascii_to_char(LOCKED_DOOR_TILE) {
+            entity_builder = entity_builder.with(LockedDoor {});
+        }
+
         match exit_data_for_tile(room_data, tile) {
             Some(exit_data) => entity_builder = entity_builder.with(ExitTrigger::new(exit_data.direction, exit_data.to_room)),
             _ => {}
@@ -335,23 +533,24 @@ pub fn get_tile_data_from_ascii_char(ascii_char: u8) -> TileData {
 
 fn map_ascii_to_char(ascii_char: u8) -> char {
     match ascii_char {
-        178 => '\u{2593}', // wall
-        205 => '\u{2550}', // horiz double pipe
-        219 => '\u{2588}', // solid wall
-        218 => '\u{250C}', // upper left single pipe
-        196 => '\u{2500}', // horiz single pipe
-        191 => '\u{2510}', // upper right single pipe
-        192 => '\u{2514}', // lower left single pipe
-        217 => '\u{2518}', // lower right single pipe
-        177 => '\u{2592}', // bush??
-        176 => '\u{2591}', // bush??
-        179 => '\u{2502}', // vertical single pipe
-        226 => '\u{0393}', // r shape?
-        224 => '\u{03B1}', // a (alpha shape)
-        195 => '\u{251C}', // left t pipe
-        107 => '\u{006B}', // k shape (kobold?)
-        98 => 'b',         // b shape (bat?)
-        247 => '\u{2248}', // approx equals
+        178 => '\u{2593}',              // wall
+        205 => '\u{2550}',              // horiz double pipe
+        219 => '\u{2588}',              // solid wall
+        218 => '\u{250C}',              // upper left single pipe
+        196 => '\u{2500}',              // horiz single pipe
+        191 => '\u{2510}',              // upper right single pipe
+        192 => '\u{2514}',              // lower left single pipe
+        217 => '\u{2518}',              // lower right single pipe
+        177 => '\u{2592}',              // bush??
+        176 => '\u{2591}',              // bush??
+        179 => '\u{2502}',              // vertical single pipe
+        226 => '\u{0393}',              // r shape?
+        224 => '\u{03B1}',              // a (alpha shape)
+        195 => '\u{251C}',              // left t pipe
+        107 => '\u{006B}',              // k shape (kobold?)
+        98 => 'b',                      // b shape (bat?)
+        247 => '\u{2248}',              // approx equals
+        LOCKED_DOOR_TILE => '\u{2261}', // triple bar, locked door
         _ => ascii_char.into(),
     }
 }
@@ -364,3 +563,103 @@ fn map_ascii_to_collision(ascii_char: u8) -> Collision {
         _ => Collision::Enabled,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exit(direction: ExitDirection, to_room: i32) -> ExitData {
+        ExitData {
+            direction: direction,
+            to_room: to_room,
+        }
+    }
+
+    // each room is just its exits
+    fn test_world(room_exits: Vec<Vec<ExitData>>) -> World {
+        let mut world = World::new();
+        let room_datas: Vec<RoomData> = room_exits
+            .into_iter()
+            .map(|exits| {
+                let mut room_data = RoomData::new();
+                room_data.exits = exits;
+                room_data
+            })
+            .collect();
+        world.insert(room_datas);
+        world.insert(Vec::<RoomRedirection>::new());
+        world
+    }
+
+    fn route_directions(route: &[ExitData]) -> Vec<ExitDirection> {
+        route.iter().map(|exit_data| exit_data.direction).collect()
+    }
+
+    #[test]
+    fn find_route_takes_the_fewest_exits() {
+        // 0 -> 1 -> 2 the long way round, or straight up from 0 to 2
+        let world = test_world(vec![
+            vec![exit(ExitDirection::North, 1), exit(ExitDirection::Up, 2)],
+            vec![exit(ExitDirection::East, 2)],
+            vec![exit(ExitDirection::Down, 0)],
+        ]);
+
+        let (destination, route) = find_route(&world, 0, |room| room == 2).unwrap();
+        assert_eq!(destination, 2);
+        assert_eq!(route_directions(&route), vec![ExitDirection::Up]);
+    }
+
+    #[test]
+    fn find_route_goes_through_several_rooms() {
+        let world = test_world(vec![
+            vec![exit(ExitDirection::North, 1)],
+            vec![exit(ExitDirection::South, 0), exit(ExitDirection::East, 2)],
+            vec![exit(ExitDirection::West, 1), exit(ExitDirection::Down, 3)],
+            vec![],
+        ]);
+
+        let (destination, route) = find_route(&world, 0, |room| room == 3).unwrap();
+        assert_eq!(destination, 3);
+        assert_eq!(
+            route_directions(&route),
+            vec![ExitDirection::North, ExitDirection::East, ExitDirection::Down]
+        );
+        assert_eq!(route.last().unwrap().to_room, 3);
+    }
+
+    #[test]
+    fn find_route_skips_the_starting_room() {
+        let world = test_world(vec![vec![exit(ExitDirection::North, 1)], vec![exit(ExitDirection::South, 0)]]);
+
+        let (destination, route) = find_route(&world, 0, |_room| true).unwrap();
+        assert_eq!(destination, 1);
+        assert_eq!(route.len(), 1);
+    }
+
+    #[test]
+    fn find_route_without_a_way_there() {
+        let world = test_world(vec![
+            vec![exit(ExitDirection::North, 1)],
+            vec![exit(ExitDirection::South, 0)],
+            vec![],
+        ]);
+
+        assert!(find_route(&world, 0, |room| room == 2).is_none());
+    }
+
+    #[test]
+    fn find_route_follows_redirected_rooms() {
+        // once room 1 is redirected to 3 it has 3's exits, which lead on to 2
+        let mut world = test_world(vec![
+            vec![exit(ExitDirection::North, 1)],
+            vec![exit(ExitDirection::South, 0)],
+            vec![],
+            vec![exit(ExitDirection::South, 0), exit(ExitDirection::Down, 2)],
+        ]);
+        assert!(find_route(&world, 0, |room| room == 2).is_none());
+
+        add_room_redirection(&mut world, 1, 3);
+        let (_destination, route) = find_route(&world, 0, |room| room == 2).unwrap();
+        assert_eq!(route_directions(&route), vec![ExitDirection::North, ExitDirection::Down]);
+    }
+}
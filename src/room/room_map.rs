@@ -0,0 +1,207 @@
+use rltk::{Algorithm2D, BaseMap, Point, SmallVec};
+use specs::prelude::*;
+use specs::storage::MaskedStorage;
+use std::ops::Deref;
+
+use crate::components::{ColliderComponent, Movement, Position};
+
+pub const ROOM_WIDTH: i32 = 24;
+pub const ROOM_HEIGHT: i32 = 18;
+
+// which tiles of the current room block movement (and sight), built from the collider tiles
+pub struct RoomMap {
+    blocked: Vec<bool>,
+}
+
+impl RoomMap {
+    // anything that moves (the player, enemies) is left out, only the walls count
+    pub fn from_colliders<'a, D: Deref<Target = MaskedStorage<Movement>>>(
+        positions: &ReadStorage<'a, Position>,
+        colliders: &ReadStorage<'a, ColliderComponent>,
+        movements: &Storage<'a, Movement, D>,
+    ) -> Self {
+        let mut room_map = Self {
+            blocked: vec![false; (ROOM_WIDTH * ROOM_HEIGHT) as usize],
+        };
+
+        for (position, _collider, _not_moving) in (positions, colliders, !movements).join() {
+            if let Some(index) = room_map.index_of(position) {
+                room_map.blocked[index] = true;
+            }
+        }
+        room_map
+    }
+
+    // blocks one more tile, ie. stairs that shouldn't be walked over by accident
+    pub fn block(&mut self, position: &Position) {
+        if let Some(index) = self.index_of(position) {
+            self.blocked[index] = true;
+        }
+    }
+
+    pub fn index_of(&self, position: &Position) -> Option<usize> {
+        if position.x < 0 || position.x >= ROOM_WIDTH || position.y < 0 || position.y >= ROOM_HEIGHT {
+            return None;
+        }
+        Some((position.y * ROOM_WIDTH + position.x) as usize)
+    }
+
+    pub fn position_of(&self, index: usize) -> Position {
+        Position {
+            x: index as i32 % ROOM_WIDTH,
+            y: index as i32 / ROOM_WIDTH,
+        }
+    }
+
+    // the first step from start towards end, None if there's no way through
+    pub fn next_step(&self, start: &Position, end: &Position) -> Option<Position> {
+        self.find_path(start, end).and_then(|path| path.first().cloned())
+    }
+
+    // every step from start to end, not counting start itself
+    pub fn find_path(&self, start: &Position, end: &Position) -> Option<Vec<Position>> {
+        let (start_index, end_index) = match (self.index_of(start), self.index_of(end)) {
+            (Some(start_index), Some(end_index)) => (start_index, end_index),
+            _ => return None,
+        };
+
+        let path = rltk::a_star_search(start_index, end_index, self);
+        if !path.success {
+            return None;
+        }
+        Some(path.steps.iter().skip(1).map(|step| self.position_of(*step)).collect())
+    }
+
+    // the open neighbour that gets furthest from the threat, None when cornered
+    pub fn step_away(&self, start: &Position, threat: &Position) -> Option<Position> {
+        let start_index = self.index_of(start)?;
+        let current_distance = Position::distance_sq(start, threat);
+
+        let mut best: Option<Position> = None;
+        let mut best_distance = current_distance;
+        for (neighbour_index, _cost) in self.get_available_exits(start_index) {
+            let neighbour = self.position_of(neighbour_index);
+            let distance = Position::distance_sq(&neighbour, threat);
+            if distance > best_distance {
+                best_distance = distance;
+                best = Some(neighbour);
+            }
+        }
+        best
+    }
+}
+
+impl BaseMap for RoomMap {
+    fn is_opaque(&self, index: usize) -> bool {
+        self.blocked[index]
+    }
+
+    fn get_available_exits(&self, index: usize) -> SmallVec<[(usize, f32); 10]> {
+        let mut exits = SmallVec::new();
+        let position = self.position_of(index);
+        for delta_y in -1..=1 {
+            for delta_x in -1..=1 {
+                if delta_x == 0 && delta_y == 0 {
+                    continue;
+                }
+
+                let neighbour = Position {
+                    x: position.x + delta_x,
+                    y: position.y + delta_y,
+                };
+                if let Some(neighbour_index) = self.index_of(&neighbour) {
+                    if !self.blocked[neighbour_index] {
+                        // diagonals cost a little more so paths don't zig zag
+                        let cost = if delta_x != 0 && delta_y != 0 { 1.45 } else { 1.0 };
+                        exits.push((neighbour_index, cost));
+                    }
+                }
+            }
+        }
+        exits
+    }
+
+    fn get_pathing_distance(&self, index1: usize, index2: usize) -> f32 {
+        let (position1, position2) = (self.position_of(index1), self.position_of(index2));
+        rltk::DistanceAlg::Pythagoras.distance2d(Point::new(position1.x, position1.y), Point::new(position2.x, position2.y))
+    }
+}
+
+impl Algorithm2D for RoomMap {
+    fn dimensions(&self) -> Point {
+        Point::new(ROOM_WIDTH, ROOM_HEIGHT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_room() -> RoomMap {
+        RoomMap {
+            blocked: vec![false; (ROOM_WIDTH * ROOM_HEIGHT) as usize],
+        }
+    }
+
+    fn at(x: i32, y: i32) -> Position {
+        Position { x: x, y: y }
+    }
+
+    // positions don't print, so compare them as pairs
+    fn xy(position: &Position) -> (i32, i32) {
+        (position.x, position.y)
+    }
+
+    fn path_xy(path: Option<Vec<Position>>) -> Option<Vec<(i32, i32)>> {
+        path.map(|path| path.iter().map(xy).collect())
+    }
+
+    #[test]
+    fn find_path_leaves_out_the_start() {
+        let path = open_room().find_path(&at(0, 0), &at(3, 0));
+        assert_eq!(path_xy(path), Some(vec![(1, 0), (2, 0), (3, 0)]));
+    }
+
+    #[test]
+    fn find_path_goes_around_walls() {
+        // a wall down x = 5 with a gap at the bottom
+        let mut room_map = open_room();
+        for y in 0..ROOM_HEIGHT - 1 {
+            room_map.block(&at(5, y));
+        }
+
+        let path = room_map.find_path(&at(2, 0), &at(8, 0)).unwrap();
+        assert_eq!(path.last().map(xy), Some((8, 0)));
+        assert!(path.iter().all(|step| step.x != 5 || step.y == ROOM_HEIGHT - 1));
+        assert_eq!(room_map.next_step(&at(2, 0), &at(8, 0)).as_ref().map(xy), path.first().map(xy));
+    }
+
+    #[test]
+    fn find_path_without_a_way_through() {
+        let mut room_map = open_room();
+        for y in 0..ROOM_HEIGHT {
+            room_map.block(&at(5, y));
+        }
+
+        assert!(room_map.find_path(&at(2, 0), &at(8, 0)).is_none());
+        assert!(room_map.find_path(&at(2, 0), &at(ROOM_WIDTH, 0)).is_none());
+    }
+
+    #[test]
+    fn step_away_moves_further_from_the_threat() {
+        let start = at(5, 5);
+        let threat = at(4, 5);
+        let step = open_room().step_away(&start, &threat).unwrap();
+        assert_eq!(step.x, 6);
+        assert!(Position::distance_sq(&step, &threat) > Position::distance_sq(&start, &threat));
+    }
+
+    #[test]
+    fn step_away_when_cornered() {
+        let mut room_map = open_room();
+        room_map.block(&at(1, 0));
+        room_map.block(&at(0, 1));
+
+        assert!(room_map.step_away(&at(0, 0), &at(1, 1)).is_none());
+    }
+}
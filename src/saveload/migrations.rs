@@ -0,0 +1,388 @@
+use serde_json::{json, Value};
+
+use super::SaveError;
+use crate::items::ItemFlags;
+
+// bump this whenever SaveData (or anything it contains, like DynamicRoomData or the ItemFlags bits) changes,
+// and add a migration that upgrades the previous version. migrations write out the values as they were at that
+// version rather than using the game's, so later changes to the game can't change what an old save upgrades to.
+// loading ignores fields SaveData doesn't have any more, so dropping one doesn't need a migration
+pub const SAVE_VERSION: u32 = 11;
+
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+// MIGRATIONS[n] upgrades a version n save to version n + 1
+const MIGRATIONS: [Migration; SAVE_VERSION as usize] = [
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    unchanged,
+    migrate_v5_to_v6,
+    migrate_v6_to_v7,
+    migrate_v7_to_v8,
+    unchanged,
+    migrate_v9_to_v10,
+    migrate_v10_to_v11,
+];
+
+pub fn get_save_version(save: &Value) -> u32 {
+    // saves from before the header existed are version 0
+    match save.pointer("/header/version").and_then(|version| version.as_u64()) {
+        Some(version) => version as u32,
+        None => 0,
+    }
+}
+
+pub fn migrate(save: &mut Value) -> Result<(), SaveError> {
+    let version = get_save_version(save);
+    if version > SAVE_VERSION {
+        return Err(SaveError::NewerVersion { version: version });
+    }
+
+    for from_version in version..SAVE_VERSION {
+        MIGRATIONS[from_version as usize](save).map_err(|reason| SaveError::Migration {
+            version: from_version,
+            reason: reason,
+        })?;
+        save["header"]["version"] = json!(from_version + 1);
+    }
+    Ok(())
+}
+
+// v0 kept the metadata at the top level (or didn't have any, along with no play time)
+fn migrate_v0_to_v1(save: &mut Value) -> Result<(), String> {
+    let save_object = save.as_object_mut().ok_or("save isn't an object")?;
+    let current_room = save_object.get("current_room").cloned().ok_or("missing current room")?;
+
+    let metadata = match save_object.remove("metadata") {
+        Some(metadata) => metadata,
+        None => json!({
+            "slot": "",
+            "room": current_room,
+            "play_time": 0,
+            "treasures": [],
+            "timestamp": 0,
+        }),
+    };
+
+    save_object.entry("play_time").or_insert(json!(0.0));
+    save_object.insert("header".to_string(), json!({ "version": 0, "metadata": metadata }));
+    Ok(())
+}
+
+// v1 didn't keep score
+fn migrate_v1_to_v2(save: &mut Value) -> Result<(), String> {
+    let save_object = save.as_object_mut().ok_or("save isn't an object")?;
+    save_object.insert("score".to_string(), json!({ "treasures": 0, "kills": 0, "secrets": 0 }));
+    Ok(())
+}
+
+// v2 didn't remember which treasures were already scored
+fn migrate_v2_to_v3(save: &mut Value) -> Result<(), String> {
+    let score = save
+        .get_mut("score")
+        .and_then(|score| score.as_object_mut())
+        .ok_or("missing score")?;
+    score.insert("collected".to_string(), json!({ "bits": 0 }));
+    Ok(())
+}
+
+// v3 players could carry anything, give them the default capacity
+fn migrate_v3_to_v4(save: &mut Value) -> Result<(), String> {
+    add_player_component(save, "CarryCapacity", json!({ "max_weight": 20 }))
+}
+
+// v4 to v5 and v8 to v9 only changed the room usables, which aren't kept in the saves any more
+fn unchanged(_save: &mut Value) -> Result<(), String> {
+    Ok(())
+}
+
+// v5 players saw everything, give them a viewshed (it gets filled in on the first frame)
+fn migrate_v5_to_v6(save: &mut Value) -> Result<(), String> {
+    // one entry per tile of the 24 x 18 room, room -1 means nothing has been seen yet
+    let viewshed = json!({
+        "range": 24,
+        "room": -1,
+        "visible": vec![false; 24 * 18],
+        "remembered": vec![false; 24 * 18],
+    });
+    add_player_component(save, "Viewshed", viewshed)
+}
+
+// v6 didn't have a seed and did fixed damage, a fixed amount is just dice with nothing but a bonus
+fn migrate_v6_to_v7(save: &mut Value) -> Result<(), String> {
+    let save_object = save.as_object_mut().ok_or("save isn't an object")?;
+    // the state of a generator seeded with 0
+    let rng = json!({ "rng": { "w": 2909580550u32, "x": 4185125612u32, "y": 1171109249u32, "z": 1934028935u32 } });
+    save_object.insert("seed".to_string(), json!(0));
+    save_object.insert("rng".to_string(), rng);
+
+    // a save without any fighters doesn't need its damage converting
+    if let Some(applies_damages) = save
+        .pointer_mut("/components/AppliesDamage")
+        .and_then(|applies_damages| applies_damages.as_array_mut())
+    {
+        for entity_data in applies_damages.iter_mut() {
+            let applies_damage = &mut entity_data["components"][0];
+            if let Some(damage) = applies_damage.get("damage").and_then(|damage| damage.as_i64()) {
+                applies_damage["damage"] = json!({ "n_dice": 0, "die_type": 0, "bonus": damage });
+            }
+        }
+    }
+    Ok(())
+}
+
+// v7 players hit hard without any equipment, so drop them to the bare handed damage and put on the sword
+// and helmet if they were carrying them
+fn migrate_v7_to_v8(save: &mut Value) -> Result<(), String> {
+    let player_markers = get_player_markers(save)?;
+    // 1d4 with a +2 to hit
+    let player_damage = json!({ "damage": { "n_dice": 1, "die_type": 4, "bonus": 0 }, "hit_bonus": 2 });
+    if let Some(applies_damages) = save
+        .pointer_mut("/components/AppliesDamage")
+        .and_then(|applies_damages| applies_damages.as_array_mut())
+    {
+        for entity_data in applies_damages.iter_mut() {
+            if player_markers.contains(&entity_data["marker"]) && !entity_data["components"][0].is_null() {
+                entity_data["components"][0] = player_damage.clone();
+            }
+        }
+    }
+
+    // there's only the one player, so their inventory is the one that counts
+    let carried_bits = save
+        .pointer("/components/InventoryComponent")
+        .and_then(|inventories| inventories.as_array())
+        .and_then(|inventories| {
+            inventories
+                .iter()
+                .find(|entity_data| player_markers.contains(&entity_data["marker"]))
+                .and_then(|entity_data| entity_data["components"][0].pointer("/items/bits"))
+                .and_then(|bits| bits.as_u64())
+        })
+        .unwrap_or(0) as u32;
+    let equipped = ItemFlags::from_bits_truncate(carried_bits) & (ItemFlags::SWORD | ItemFlags::HELMET);
+    add_player_component(save, "Equipment", json!({ "items": equipped }))
+}
+
+// rooms in v9 (and some earlier) saves could be missing who's been banished from them, that went into the room
+// data without a version bump
+fn migrate_v9_to_v10(save: &mut Value) -> Result<(), String> {
+    let dynamic_rooms = save
+        .get_mut("dynamic_rooms")
+        .and_then(|dynamic_rooms| dynamic_rooms.as_array_mut())
+        .ok_or("missing dynamic rooms")?;
+    for dynamic_room in dynamic_rooms.iter_mut() {
+        // saves that already have it keep it, the banished list has changed since the game started
+        let dynamic_room = dynamic_room.as_object_mut().ok_or("room isn't an object")?;
+        dynamic_room.entry("banished").or_insert(json!([]));
+    }
+    Ok(())
+}
+
+// v10 wands kept opening the secret passages, now they only work while the room's passage_open flag isn't set,
+// so passages that were already opened need the flag
+fn migrate_v10_to_v11(save: &mut Value) -> Result<(), String> {
+    let opened_rooms: Vec<i64> = save
+        .get("room_redirections")
+        .and_then(|redirections| redirections.as_array())
+        .ok_or("missing room redirections")?
+        .iter()
+        .filter_map(|redirection| redirection["original_room"].as_i64())
+        .collect();
+
+    let dynamic_rooms = save
+        .get_mut("dynamic_rooms")
+        .and_then(|dynamic_rooms| dynamic_rooms.as_array_mut())
+        .ok_or("missing dynamic rooms")?;
+    for dynamic_room in dynamic_rooms.iter_mut() {
+        let room = dynamic_room
+            .get("room")
+            .and_then(|room| room.as_i64())
+            .ok_or("missing room index")?;
+        if [66, 76].contains(&room) && opened_rooms.contains(&room) {
+            let flags = dynamic_room
+                .as_object_mut()
+                .ok_or("room isn't an object")?
+                .entry("flags")
+                .or_insert(json!([]))
+                .as_array_mut()
+                .ok_or("flags aren't a list")?;
+            flags.push(json!("passage_open"));
+        }
+    }
+    Ok(())
+}
+
+fn get_player_markers(save: &Value) -> Result<Vec<Value>, String> {
+    let players = save
+        .pointer("/components/Player")
+        .and_then(|players| players.as_array())
+        .ok_or("missing players")?;
+    Ok(players
+        .iter()
+        .filter(|entity_data| !entity_data["components"][0].is_null())
+        .map(|entity_data| entity_data["marker"].clone())
+        .collect())
+}
+
+fn add_player_component(save: &mut Value, component_name: &str, component: Value) -> Result<(), String> {
+    // each component type is a list of { marker, components: [component or null] } for every marked entity
+    let player_components: Vec<Value> = get_player_markers(save)?
+        .into_iter()
+        .map(|marker| json!({ "marker": marker, "components": [component] }))
+        .collect();
+
+    let components = save
+        .get_mut("components")
+        .and_then(|components| components.as_object_mut())
+        .ok_or("missing components")?;
+    components.insert(component_name.to_string(), Value::Array(player_components));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a save from before the header, with a player carrying the sword and key and an enemy next to them
+    fn v0_save() -> Value {
+        json!({
+            "current_room": 66,
+            "components": {
+                "Player": [{ "marker": 1, "components": [{}] }, { "marker": 2, "components": [null] }],
+                "InventoryComponent": [{ "marker": 1, "components": [{ "items": { "bits": 16 | 32 } }] }],
+                "AppliesDamage": [
+                    { "marker": 1, "components": [{ "damage": 5, "hit_bonus": 0 }] },
+                    { "marker": 2, "components": [{ "damage": 3, "hit_bonus": 0 }] },
+                ],
+            },
+            "room_redirections": [{ "original_room": 66, "new_room": 84 }],
+            "dynamic_rooms": [
+                { "room": 0, "items": [], "descriptions": [], "enemies": [], "map": null },
+                { "room": 66, "items": [], "descriptions": [], "enemies": [], "map": null },
+                { "room": 68, "items": [], "descriptions": [], "enemies": [], "map": null },
+            ],
+        })
+    }
+
+    fn find_room(save: &Value, room: i64) -> &Value {
+        save["dynamic_rooms"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|dynamic_room| dynamic_room["room"].as_i64() == Some(room))
+            .unwrap()
+    }
+
+    #[test]
+    fn migrates_v0_to_the_current_version() {
+        let mut save = v0_save();
+        migrate(&mut save).unwrap();
+
+        assert_eq!(get_save_version(&save), SAVE_VERSION);
+        assert_eq!(save.pointer("/header/metadata/room"), Some(&json!(66)));
+        assert_eq!(save["score"]["collected"], json!({ "bits": 0 }));
+        assert_eq!(save["seed"], json!(0));
+    }
+
+    #[test]
+    fn migrated_player_gets_the_components_they_were_missing() {
+        let mut save = v0_save();
+        migrate(&mut save).unwrap();
+
+        assert_eq!(
+            save.pointer("/components/CarryCapacity/0/components/0/max_weight"),
+            Some(&json!(20))
+        );
+        assert_eq!(save.pointer("/components/Equipment/0/components/0/items/bits"), Some(&json!(16)));
+
+        let viewshed: crate::visibility::Viewshed =
+            serde_json::from_value(save["components"]["Viewshed"][0]["components"][0].clone()).unwrap();
+        assert_eq!(viewshed.range, 24);
+    }
+
+    #[test]
+    fn migrated_damage_becomes_dice() {
+        let mut save = v0_save();
+        migrate(&mut save).unwrap();
+
+        let damages = &save["components"]["AppliesDamage"];
+        assert_eq!(
+            damages[0]["components"][0],
+            json!({ "damage": { "n_dice": 1, "die_type": 4, "bonus": 0 }, "hit_bonus": 2 })
+        );
+        assert_eq!(
+            damages[1]["components"][0]["damage"],
+            json!({ "n_dice": 0, "die_type": 0, "bonus": 3 })
+        );
+    }
+
+    #[test]
+    fn migrated_rng_is_seeded_with_zero() {
+        let mut save = v0_save();
+        migrate(&mut save).unwrap();
+
+        let mut rng: rltk::RandomNumberGenerator = serde_json::from_value(save["rng"].clone()).unwrap();
+        let mut seeded = rltk::RandomNumberGenerator::seeded(0);
+        assert_eq!(rng.roll_dice(1, 1000), seeded.roll_dice(1, 1000));
+    }
+
+    #[test]
+    fn migrated_rooms_only_get_their_state() {
+        let mut save = v0_save();
+        migrate(&mut save).unwrap();
+
+        // the usables, darkness and long descriptions come from dynrooms.json rather than the save
+        let courtyard = find_room(&save, 0);
+        assert!(courtyard.get("usables").is_none());
+        assert!(courtyard.get("dark").is_none());
+        assert!(courtyard.get("long_description").is_none());
+
+        let passage = find_room(&save, 68);
+        assert!(passage.get("dark").is_none());
+        assert_eq!(passage["banished"], json!([]));
+
+        // the passage was already open, so the wand shouldn't open it again
+        let quarters = find_room(&save, 66);
+        assert_eq!(quarters["flags"], json!(["passage_open"]));
+        assert!(quarters.get("usables").is_none());
+    }
+
+    #[test]
+    fn migration_keeps_fields_the_save_already_has() {
+        let mut save = v0_save();
+        save["dynamic_rooms"][2]["banished"] = json!(["bat"]);
+        migrate(&mut save).unwrap();
+
+        let passage = find_room(&save, 68);
+        assert_eq!(passage["banished"], json!(["bat"]));
+    }
+
+    #[test]
+    fn current_saves_are_left_alone() {
+        let mut save = json!({ "header": { "version": SAVE_VERSION } });
+        let unchanged = save.clone();
+        migrate(&mut save).unwrap();
+        assert_eq!(save, unchanged);
+    }
+
+    #[test]
+    fn newer_saves_are_refused() {
+        let mut save = json!({ "header": { "version": SAVE_VERSION + 1 } });
+        match migrate(&mut save) {
+            Err(SaveError::NewerVersion { version }) => assert_eq!(version, SAVE_VERSION + 1),
+            _ => panic!("a save from a newer version shouldn't load"),
+        }
+    }
+
+    #[test]
+    fn broken_saves_say_which_migration_failed() {
+        let mut save = json!({ "header": { "version": 2 } });
+        match migrate(&mut save) {
+            Err(SaveError::Migration { version, .. }) => assert_eq!(version, 2),
+            _ => panic!("a save without a score should fail to migrate"),
+        }
+    }
+}
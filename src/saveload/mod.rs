@@ -0,0 +1,529 @@
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
+use specs::prelude::*;
+use specs::saveload::*;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ai::{AiAmbush, AiFleeWhenHurt, AiGuard, AiMoveToPlayer, AiWander};
+use crate::commands::{CommandArgs, CommandRegistry, TextCommand};
+use crate::components::*;
+use crate::consumables::ActiveBuff;
+use crate::equipment::Equipment;
+use crate::game::{CurrentRoom, DynamicMarker, PlayTime};
+use crate::items::ItemFlags;
+use crate::random::GameSeed;
+use crate::render::Renderable;
+use crate::room::{BelongsToRoom, DynamicRoomData, RoomRedirection};
+use crate::score::{Score, ScoreValue};
+use crate::visibility::Viewshed;
+use crate::StateAction;
+
+mod migrations;
+use migrations::*;
+
+const SAVE_DIRECTORY: &str = "./saves";
+pub const QUICKSAVE_SLOT: &str = "quicksave";
+const AUTOSAVE_SLOTS: [&str; 3] = ["autosave1", "autosave2", "autosave3"];
+
+// the bits of a save that get shown when listing the slots
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SaveMetadata {
+    pub slot: String,
+    pub room: i32,
+    pub play_time: u64, // seconds
+    pub treasures: Vec<String>,
+    pub timestamp: u64, // seconds since the unix epoch
+}
+
+impl SaveMetadata {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} (room {}, {}:{:02}, {} treasures)",
+            self.slot,
+            self.room,
+            self.play_time / 60,
+            self.play_time % 60,
+            self.treasures.len()
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveHeader {
+    version: u32,
+    metadata: SaveMetadata,
+}
+
+// everything that isn't attached to an entity, plus the marked entities' components keyed by component name
+#[derive(Serialize, Deserialize)]
+struct SaveData {
+    header: SaveHeader,
+    play_time: f32,
+    score: Score,
+    seed: GameSeed,
+    rng: rltk::RandomNumberGenerator, // where the rolls had got to, so loading doesn't replay the same luck
+    current_room: i32,
+    room_redirections: Vec<RoomRedirection>,
+    dynamic_rooms: Vec<DynamicRoomData>,
+    components: BTreeMap<String, serde_json::Value>,
+}
+
+// just the header of a save, serde skips over everything else without building it
+#[derive(Deserialize)]
+struct SaveDataHeader {
+    header: Option<SaveHeader>, // v0 saves don't have one
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    NotFound { slot: String },
+    NoSaves,
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    NewerVersion { version: u32 },
+    Migration { version: u32, reason: String },
+    Components { component: String, reason: String },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::NotFound { slot } => write!(f, "there is no save called {}", slot),
+            SaveError::NoSaves => write!(f, "there are no saves to load"),
+            SaveError::Io(error) => write!(f, "couldn't access the save: {}", error),
+            SaveError::Json(error) => write!(f, "the save is damaged: {}", error),
+            SaveError::NewerVersion { version } => write!(
+                f,
+                "the save is from a newer version of the game (save version {}, supported up to {})",
+                version, SAVE_VERSION
+            ),
+            SaveError::Migration { version, reason } => write!(f, "couldn't upgrade the save from version {}: {}", version, reason),
+            SaveError::Components { component, reason } => write!(f, "couldn't load {}: {}", component, reason),
+        }
+    }
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(error: std::io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(error: serde_json::Error) -> Self {
+        SaveError::Json(error)
+    }
+}
+
+// specs only implements the saveload traits for tuples of up to 16 storages, so do them one at a time
+macro_rules! serialize_individually {
+    ($world:expr, $components:expr, $( $type:ty ),*) => {
+        $(
+        let value = SerializeComponents::<NoError, SimpleMarker<DynamicMarker>>::serialize(
+            &($world.read_storage::<$type>(),),
+            &$world.entities(),
+            &$world.read_storage::<SimpleMarker<DynamicMarker>>(),
+            serde_json::value::Serializer,
+        )
+        .expect("failed to serialize components");
+        $components.insert(stringify!($type).to_string(), value);
+        )*
+    };
+}
+
+macro_rules! deserialize_individually {
+    ($world:expr, $components:expr, $( $type:ty ),*) => {
+        $(
+        if let Some(value) = $components.get(stringify!($type)) {
+            DeserializeComponents::<NoError, _>::deserialize(
+                &mut ($world.write_storage::<$type>(),),
+                &$world.entities(),
+                &mut $world.write_storage::<SimpleMarker<DynamicMarker>>(),
+                &mut $world.write_resource::<SimpleMarkerAllocator<DynamicMarker>>(),
+                value,
+            )
+            .map_err(|error| SaveError::Components {
+                component: stringify!($type).to_string(),
+                reason: error.to_string(),
+            })?;
+        }
+        )*
+    };
+}
+
+pub fn is_valid_slot_name(slot: &str) -> bool {
+    !slot.is_empty() && slot.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn slot_path(slot: &str) -> PathBuf {
+    Path::new(SAVE_DIRECTORY).join(format!("{}.json", slot))
+}
+
+pub fn has_save_game(slot: &str) -> bool {
+    slot_path(slot).exists()
+}
+
+pub fn has_any_save_game() -> bool {
+    match std::fs::read_dir(SAVE_DIRECTORY) {
+        Ok(mut dir_entries) => dir_entries.next().is_some(),
+        Err(_) => false,
+    }
+}
+
+// reads a save and brings it up to the current version
+fn read_save(path: &Path) -> Result<serde_json::Value, SaveError> {
+    let f = File::open(path)?;
+    let mut save: serde_json::Value = serde_json::from_reader(f)?;
+    migrate(&mut save)?;
+    Ok(save)
+}
+
+// reads only as much of a save as listing it needs, so the components aren't built and the migrations don't run
+fn read_save_header(path: &Path) -> Result<SaveHeader, SaveError> {
+    let f = BufReader::new(File::open(path)?);
+    let header = match serde_json::from_reader::<_, SaveDataHeader>(f)?.header {
+        Some(header) => header,
+        // the header gets made by the first migration
+        None => serde_json::from_value::<SaveHeader>(read_save(path)?["header"].take())?,
+    };
+
+    if header.version > SAVE_VERSION {
+        return Err(SaveError::NewerVersion { version: header.version });
+    }
+    Ok(header)
+}
+
+pub fn list_saves() -> Vec<SaveMetadata> {
+    let mut saves = Vec::new();
+    if let Ok(dir_entries) = std::fs::read_dir(SAVE_DIRECTORY) {
+        for dir_entry in dir_entries.flatten() {
+            // damaged saves, or ones from a newer version, are left off the list
+            let path = dir_entry.path();
+            if let Ok(header) = read_save_header(&path) {
+                let mut metadata = header.metadata;
+                if metadata.slot.is_empty() {
+                    metadata.slot = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+                }
+                saves.push(metadata);
+            }
+        }
+    }
+
+    // newest first
+    saves.sort_by_key(|save| std::cmp::Reverse(save.timestamp));
+    saves
+}
+
+pub fn autosave(world: &mut World) {
+    // reuse whichever autosave slot is free or the oldest
+    let saves = list_saves();
+    let mut slot = AUTOSAVE_SLOTS[0];
+    let mut oldest_timestamp = u64::MAX;
+    for autosave_slot in AUTOSAVE_SLOTS.iter() {
+        match saves.iter().find(|save| save.slot == *autosave_slot) {
+            Some(save) => {
+                if save.timestamp < oldest_timestamp {
+                    oldest_timestamp = save.timestamp;
+                    slot = autosave_slot;
+                }
+            }
+            None => {
+                slot = autosave_slot;
+                break;
+            }
+        }
+    }
+    if let Err(error) = save_game(world, slot) {
+        for (_player, active_description) in (
+            &world.read_storage::<Player>(),
+            &mut world.write_storage::<ActiveDescriptionComponent>(),
+        )
+            .join()
+        {
+            active_description.set(format!("autosave failed: {}", error).as_str());
+        }
+    }
+}
+
+fn create_metadata(world: &World, slot: &str) -> SaveMetadata {
+    let mut treasures = Vec::new();
+    for (_player, inventory) in (&world.read_storage::<Player>(), &world.read_storage::<InventoryComponent>()).join() {
+        for treasure in (inventory.items() & ItemFlags::TREASURES).each() {
+            treasures.push(crate::items::get_item_display_name(treasure, world));
+        }
+    }
+
+    SaveMetadata {
+        slot: slot.to_string(),
+        room: world.fetch::<CurrentRoom>().get_room_index(),
+        play_time: world.fetch::<PlayTime>().0 as u64,
+        treasures: treasures,
+        timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+    }
+}
+
+pub fn save_game(world: &mut World, slot: &str) -> Result<(), SaveError> {
+    let mut components = BTreeMap::new();
+    serialize_individually!(
+        world,
+        components,
+        Position,
+        Renderable,
+        Player,
+        Movement,
+        ColliderComponent,
+        BelongsToRoom,
+        ActiveDescriptionComponent,
+        ConditionalDescription,
+        AiMoveToPlayer,
+        AiWander,
+        AiAmbush,
+        AiFleeWhenHurt,
+        AiGuard,
+        Viewshed,
+        InventoryComponent,
+        CarryCapacity,
+        Equipment,
+        ActiveBuff,
+        PickupTrigger,
+        CombatStats,
+        AppliesDamage,
+        DebugName,
+        CombatLog,
+        Description,
+        PlayerInputComponent,
+        PlayerInputMappingComponent,
+        PlayerTextInputComponent,
+        DebugHudComponent,
+        InventoryHudComponent,
+        ScoreValue
+    );
+
+    let save_data = SaveData {
+        header: SaveHeader {
+            version: SAVE_VERSION,
+            metadata: create_metadata(world, slot),
+        },
+        play_time: world.fetch::<PlayTime>().0,
+        score: (*world.fetch::<Score>()).clone(),
+        seed: *world.fetch::<GameSeed>(),
+        rng: (*world.fetch::<rltk::RandomNumberGenerator>()).clone(),
+        current_room: world.fetch::<CurrentRoom>().get_room_index(),
+        room_redirections: (*world.fetch::<Vec<RoomRedirection>>()).clone(),
+        dynamic_rooms: (*world.fetch::<Vec<DynamicRoomData>>()).clone(),
+        components: components,
+    };
+
+    std::fs::create_dir_all(SAVE_DIRECTORY)?;
+    let writer = File::create(slot_path(slot))?;
+    serde_json::to_writer(writer, &save_data)?;
+    Ok(())
+}
+
+pub fn load_game(world: &mut World, slot: &str) -> Result<(), SaveError> {
+    if !has_save_game(slot) {
+        return Err(SaveError::NotFound { slot: slot.to_string() });
+    }
+
+    // everything up to here can fail without touching the world
+    let save = read_save(&slot_path(slot))?;
+    let save_data: SaveData = serde_json::from_value(save)?;
+
+    // throw away everything, the room map gets rebuilt from the raw room data once the entities are back
+    world.delete_all();
+    world.maintain();
+
+    world.insert(PlayTime(save_data.play_time));
+    world.insert(save_data.score);
+    world.insert(save_data.seed);
+    world.insert(save_data.rng);
+    world.insert(CurrentRoom(save_data.current_room));
+    world.insert(save_data.room_redirections);
+    world.insert(save_data.dynamic_rooms);
+
+    let components = save_data.components;
+    deserialize_individually!(
+        world,
+        components,
+        Position,
+        Renderable,
+        Player,
+        Movement,
+        ColliderComponent,
+        BelongsToRoom,
+        ActiveDescriptionComponent,
+        ConditionalDescription,
+        AiMoveToPlayer,
+        AiWander,
+        AiAmbush,
+        AiFleeWhenHurt,
+        AiGuard,
+        Viewshed,
+        InventoryComponent,
+        CarryCapacity,
+        Equipment,
+        ActiveBuff,
+        PickupTrigger,
+        CombatStats,
+        AppliesDamage,
+        DebugName,
+        CombatLog,
+        Description,
+        PlayerInputComponent,
+        PlayerInputMappingComponent,
+        PlayerTextInputComponent,
+        DebugHudComponent,
+        InventoryHudComponent,
+        ScoreValue
+    );
+
+    crate::room::create_room_map_entities(world, save_data.current_room);
+    Ok(())
+}
+
+pub fn register_commands(registry: &mut CommandRegistry) {
+    registry.register(SaveCommand {});
+    registry.register(LoadCommand {});
+    registry.register(SavesCommand {});
+}
+
+struct SaveCommand {}
+
+impl TextCommand for SaveCommand {
+    fn name(&self) -> &'static str {
+        "save"
+    }
+
+    fn help(&self) -> &'static str {
+        "save <name> saves the game, leave off the name to quicksave"
+    }
+
+    fn needs_exact_name(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String> {
+        let slot = args.arg.clone().unwrap_or_else(|| QUICKSAVE_SLOT.to_string());
+        if !is_valid_slot_name(slot.as_str()) {
+            return Some("save names can only use letters and numbers".to_string());
+        }
+
+        let result = format!("saved {}", slot);
+        world
+            .write_resource::<Vec<StateAction>>()
+            .push(StateAction::SaveGame { slot: slot });
+        Some(result)
+    }
+}
+
+struct LoadCommand {}
+
+impl TextCommand for LoadCommand {
+    fn name(&self) -> &'static str {
+        "load"
+    }
+
+    fn help(&self) -> &'static str {
+        "load <name> loads a save, leave off the name for the quicksave"
+    }
+
+    fn needs_exact_name(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String> {
+        let slot = args.arg.clone().unwrap_or_else(|| QUICKSAVE_SLOT.to_string());
+        if !is_valid_slot_name(slot.as_str()) || !has_save_game(slot.as_str()) {
+            return Some(format!("there is no save called {}", slot));
+        }
+
+        world
+            .write_resource::<Vec<StateAction>>()
+            .push(StateAction::LoadGame { slot: slot });
+        Some("loading...".to_string())
+    }
+}
+
+struct SavesCommand {}
+
+impl TextCommand for SavesCommand {
+    fn name(&self) -> &'static str {
+        "saves"
+    }
+
+    fn help(&self) -> &'static str {
+        "list the saved games, newest first"
+    }
+
+    fn execute(&self, _world: &mut World, _args: &CommandArgs) -> Option<String> {
+        let saves = list_saves();
+        if saves.is_empty() {
+            return Some("there are no saves".to_string());
+        }
+
+        let summaries: Vec<String> = saves.iter().map(|save| save.summary()).collect();
+        Some(summaries.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write_save(name: &str, save: serde_json::Value) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rust_castle_{}_{}.json", std::process::id(), name));
+        std::fs::write(&path, save.to_string()).unwrap();
+        path
+    }
+
+    fn metadata(slot: &str) -> serde_json::Value {
+        json!({ "slot": slot, "room": 3, "play_time": 90, "treasures": ["Ruby"], "timestamp": 1000 })
+    }
+
+    #[test]
+    fn the_header_is_read_without_loading_the_rest() {
+        // nothing past the header would load, so it can't have been looked at
+        let path = write_save(
+            "header",
+            json!({ "header": { "version": SAVE_VERSION, "metadata": metadata("quicksave") }, "components": "damaged" }),
+        );
+        let header = read_save_header(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(header.metadata.summary(), "quicksave (room 3, 1:30, 1 treasures)");
+    }
+
+    #[test]
+    fn saves_without_a_header_are_migrated_to_get_one() {
+        let path = write_save(
+            "v0",
+            json!({ "current_room": 7, "metadata": metadata("autosave1"), "room_redirections": [], "dynamic_rooms": [], "components": { "Player": [] } }),
+        );
+        let header = read_save_header(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(header.version, SAVE_VERSION);
+        assert_eq!(header.metadata.slot, "autosave1");
+    }
+
+    #[test]
+    fn saves_from_a_newer_version_are_not_listed() {
+        let path = write_save(
+            "newer",
+            json!({ "header": { "version": SAVE_VERSION + 1, "metadata": metadata("quicksave") } }),
+        );
+        let result = read_save_header(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        match result {
+            Err(SaveError::NewerVersion { version }) => assert_eq!(version, SAVE_VERSION + 1),
+            _ => panic!("a save from a newer version shouldn't be listed"),
+        }
+    }
+}
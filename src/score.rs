@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
+use specs::prelude::*;
+use specs::saveload::{ConvertSaveload, Marker};
+use specs_derive::{Component, ConvertSaveload};
+
+use crate::commands::{CommandArgs, CommandRegistry, TextCommand};
+use crate::items::ItemFlags;
+use crate::random::GameSeed;
+
+// points for anything that's worth something when collected or killed
+#[derive(Component, ConvertSaveload, Clone)]
+pub struct ScoreValue {
+    pub points: i32,
+}
+
+pub const SECRET_POINTS: i32 = 50;
+
+// running score for the current game
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Score {
+    pub treasures: i32,
+    pub kills: i32,
+    pub secrets: i32,
+    collected: ItemFlags, // so dropping and picking up a treasure again doesn't count twice
+}
+
+impl Score {
+    pub fn new() -> Self {
+        Self {
+            treasures: 0,
+            kills: 0,
+            secrets: 0,
+            collected: ItemFlags::EMPTY,
+        }
+    }
+
+    pub fn collect(&mut self, item: ItemFlags, points: i32) {
+        if !self.collected.contains(item) {
+            self.collected |= item;
+            self.treasures += points;
+        }
+    }
+
+    pub fn total(&self) -> i32 {
+        self.treasures + self.kills + self.secrets
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "score: {} (treasures {}, kills {}, secrets {})",
+            self.total(),
+            self.treasures,
+            self.kills,
+            self.secrets
+        )
+    }
+
+    pub fn rank(&self) -> &'static str {
+        match self.total() {
+            total if total >= 1000 => "Master Adventurer",
+            total if total >= 750 => "Knight",
+            total if total >= 500 => "Squire",
+            total if total >= 250 => "Treasure Hunter",
+            _ => "Peasant",
+        }
+    }
+}
+
+pub fn register_commands(registry: &mut CommandRegistry) {
+    registry.register(ScoreCommand {});
+}
+
+struct ScoreCommand {}
+
+impl TextCommand for ScoreCommand {
+    fn name(&self) -> &'static str {
+        "score"
+    }
+
+    fn help(&self) -> &'static str {
+        "how many points you have, and the seed to play this game again with"
+    }
+
+    fn execute(&self, world: &mut World, _args: &CommandArgs) -> Option<String> {
+        let seed = world.fetch::<GameSeed>().0;
+        Some(format!("{}, seed {}", world.fetch::<Score>().summary(), seed))
+    }
+}
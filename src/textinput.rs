@@ -1,27 +1,286 @@
-use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::collections::BTreeMap;
+use std::fs::File;
 
-pub enum TextCommand {
+pub enum ParsedCommand {
     None,
-    Some { command: String, arg: Option<String> },
+    Some {
+        command: String,
+        arg: Option<String>,    // the thing being acted on, ie. the key in "use key on door"
+        target: Option<String>, // what it's being done to, ie. the door
+    },
 }
 
-pub fn parse_input(text_command: &String) -> TextCommand {
-    let mut tokens = text_command.split_whitespace();
-    if let Some(first_token) = tokens.next() {
-        let args_string = tokens.format(" ").to_string();
-        let args = args_string.as_str();
-        if args.is_empty() {
-            return TextCommand::Some {
-                command: first_token.to_owned(),
-                arg: None,
-            };
+// the words the parser knows about, from parser.json
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ParserData {
+    pub articles: Vec<String>,
+    pub prepositions: Vec<String>,
+    pub synonyms: BTreeMap<String, String>, // word -> the verb it stands for
+}
+
+// turns typed text into a verb and its objects, "it" being the last object remembered
+pub struct TextParser {
+    data: ParserData,
+    last_object: Option<String>,
+}
+
+impl TextParser {
+    pub fn new(data: ParserData) -> Self {
+        Self {
+            data: data,
+            last_object: None,
+        }
+    }
+
+    pub fn parse(&self, text_command: &String) -> ParsedCommand {
+        let mut tokens = text_command.split_whitespace();
+        let first_token = match tokens.next() {
+            Some(first_token) => first_token.to_ascii_lowercase(),
+            None => return ParsedCommand::None,
+        };
+        let command = self.data.synonyms.get(&first_token).cloned().unwrap_or(first_token);
+
+        let mut words: Vec<&str> = tokens.filter(|token| !self.is_article(token)).collect();
+
+        // "look at book", the preposition doesn't split anything
+        if words.first().is_some_and(|word| self.is_preposition(word)) {
+            words.remove(0);
         }
 
-        return TextCommand::Some {
-            command: first_token.to_owned(),
-            arg: Some(args.to_owned()),
+        // "give goblet to fairy"
+        let (object_words, target_words) = match words.iter().position(|word| self.is_preposition(word)) {
+            Some(index) => (&words[..index], &words[index + 1..]),
+            None => (&words[..], &words[words.len()..]),
         };
+
+        return ParsedCommand::Some {
+            command: command,
+            arg: self.resolve(object_words),
+            target: self.resolve(target_words),
+        };
+    }
+
+    // what "it" means from now on, once typos have been corrected
+    pub fn remember_object(&mut self, object: &str) {
+        self.last_object = Some(object.to_string());
+    }
+
+    // joins the words back up, "it" being whatever was mentioned last
+    fn resolve(&self, words: &[&str]) -> Option<String> {
+        if words.is_empty() {
+            return None;
+        }
+
+        let text = words.join(" ");
+        if text.eq_ignore_ascii_case("it") {
+            return self.last_object.clone();
+        }
+        Some(text)
+    }
+
+    // the parser drops these from whatever is typed, so anything being compared against it has to lose them too
+    pub fn without_articles(&self, text: &str) -> String {
+        text.split_whitespace()
+            .filter(|word| !self.is_article(word))
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    fn is_article(&self, word: &str) -> bool {
+        self.data.articles.iter().any(|article| article.eq_ignore_ascii_case(word))
+    }
+
+    fn is_preposition(&self, word: &str) -> bool {
+        self.data
+            .prepositions
+            .iter()
+            .any(|preposition| preposition.eq_ignore_ascii_case(word))
+    }
+}
+
+pub fn load_text_parser(world: &mut World) {
+    let f = File::open("data/parser.json").expect("parser data not found");
+    let parser_data: ParserData = serde_json::from_reader(f).expect("failed to deserializer!");
+
+    world.insert(TextParser::new(parser_data));
+}
+
+pub enum FuzzyMatch {
+    Found(String),
+    Suggestions(Vec<String>),
+    NotFound,
+}
+
+// an exact match wins and a single near miss is taken as a typo, anything less certain comes back as suggestions
+pub fn fuzzy_match<'a>(word: &str, candidates: impl IntoIterator<Item = &'a str>) -> FuzzyMatch {
+    let max_distance = if word.len() <= 4 { 1 } else { 2 };
+
+    let mut best_distance = max_distance;
+    let mut best_matches: Vec<String> = Vec::new();
+    for candidate in candidates {
+        let distance = edit_distance(word, candidate);
+        if distance == 0 {
+            return FuzzyMatch::Found(candidate.to_string());
+        }
+        if distance > best_distance {
+            continue;
+        }
+        if distance < best_distance {
+            best_distance = distance;
+            best_matches.clear();
+        }
+        if !best_matches.iter().any(|best_match| best_match == candidate) {
+            best_matches.push(candidate.to_string());
+        }
     }
 
-    return TextCommand::None;
+    match best_matches.len() {
+        0 => FuzzyMatch::NotFound,
+        1 if best_distance == 1 => FuzzyMatch::Found(best_matches.remove(0)),
+        _ => FuzzyMatch::Suggestions(best_matches),
+    }
+}
+
+pub fn did_you_mean(suggestions: &[String]) -> String {
+    let quoted: Vec<String> = suggestions.iter().map(|suggestion| format!("'{}'", suggestion)).collect();
+    format!("Did you mean {}?", quoted.join(" or "))
+}
+
+// levenshtein distance, how many letters have to be added, removed or swapped to get from one word to the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=b_chars.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current_row = vec![i + 1];
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution_cost = if a_char.eq_ignore_ascii_case(b_char) { 0 } else { 1 };
+            let distance = usize::min(
+                previous_row[j] + substitution_cost,
+                usize::min(previous_row[j + 1] + 1, current_row[j] + 1),
+            );
+            current_row.push(distance);
+        }
+        previous_row = current_row;
+    }
+    previous_row[b_chars.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_parser() -> TextParser {
+        let mut synonyms = BTreeMap::new();
+        synonyms.insert("x".to_string(), "look".to_string());
+        synonyms.insert("get".to_string(), "take".to_string());
+        TextParser::new(ParserData {
+            articles: vec!["the".to_string(), "a".to_string()],
+            prepositions: vec!["at".to_string(), "to".to_string()],
+            synonyms,
+        })
+    }
+
+    fn parse(text_parser: &TextParser, text: &str) -> (String, Option<String>, Option<String>) {
+        match text_parser.parse(&text.to_string()) {
+            ParsedCommand::Some { command, arg, target } => (command, arg, target),
+            ParsedCommand::None => panic!("nothing parsed from {}", text),
+        }
+    }
+
+    #[test]
+    fn parse_drops_articles_and_leading_prepositions() {
+        let text_parser = test_parser();
+        assert_eq!(
+            parse(&text_parser, "look at the book"),
+            ("look".to_string(), Some("book".to_string()), None)
+        );
+        assert_eq!(
+            parse(&text_parser, "take a rusty key"),
+            ("take".to_string(), Some("rusty key".to_string()), None)
+        );
+        assert_eq!(text_parser.without_articles("the eye glasses"), "eye glasses");
+    }
+
+    #[test]
+    fn parse_splits_the_target_off() {
+        let text_parser = test_parser();
+        assert_eq!(
+            parse(&text_parser, "give the goblet to the fairy"),
+            ("give".to_string(), Some("goblet".to_string()), Some("fairy".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_swaps_synonyms_for_their_verb() {
+        let text_parser = test_parser();
+        assert_eq!(parse(&text_parser, "X book").0, "look");
+        assert_eq!(parse(&text_parser, "get lamp").0, "take");
+        assert_eq!(parse(&text_parser, "drop lamp").0, "drop");
+    }
+
+    #[test]
+    fn parse_it_means_the_remembered_object() {
+        let mut text_parser = test_parser();
+        assert_eq!(parse(&text_parser, "take it").1, None);
+
+        text_parser.remember_object("lamp");
+        assert_eq!(parse(&text_parser, "take it").1, Some("lamp".to_string()));
+        assert_eq!(parse(&text_parser, "give it to the fairy").1, Some("lamp".to_string()));
+    }
+
+    #[test]
+    fn parse_nothing_typed() {
+        match test_parser().parse(&"   ".to_string()) {
+            ParsedCommand::None => {}
+            ParsedCommand::Some { .. } => panic!("blank input shouldn't parse"),
+        }
+    }
+
+    #[test]
+    fn edit_distance_counts_changes() {
+        assert_eq!(edit_distance("look", "look"), 0);
+        assert_eq!(edit_distance("lok", "look"), 1);
+        assert_eq!(edit_distance("luk", "look"), 2);
+        assert_eq!(edit_distance("", "take"), 4);
+        assert_eq!(edit_distance("TAKE", "take"), 0);
+    }
+
+    #[test]
+    fn fuzzy_match_takes_a_single_typo() {
+        match fuzzy_match("wiled", vec!["wield", "drop"]) {
+            FuzzyMatch::Suggestions(suggestions) => assert_eq!(suggestions, vec!["wield"]),
+            _ => panic!("two letters off should only be suggested"),
+        }
+        match fuzzy_match("tkae", vec!["take", "drop"]) {
+            FuzzyMatch::NotFound => {}
+            _ => panic!("short words only allow one letter off"),
+        }
+        match fuzzy_match("tak", vec!["take", "drop"]) {
+            FuzzyMatch::Found(found) => assert_eq!(found, "take"),
+            _ => panic!("one letter off should be taken"),
+        }
+    }
+
+    #[test]
+    fn fuzzy_match_prefers_an_exact_match() {
+        match fuzzy_match("use", vec!["us", "use", "fuse"]) {
+            FuzzyMatch::Found(found) => assert_eq!(found, "use"),
+            _ => panic!("the exact match should win"),
+        }
+    }
+
+    #[test]
+    fn fuzzy_match_suggests_when_unsure() {
+        match fuzzy_match("bat", vec!["hat", "cat"]) {
+            FuzzyMatch::Suggestions(suggestions) => assert_eq!(suggestions, vec!["hat", "cat"]),
+            _ => panic!("two equally close words should both be suggested"),
+        }
+        match fuzzy_match("xyzzy", vec!["look", "take"]) {
+            FuzzyMatch::NotFound => {}
+            _ => panic!("nothing is close"),
+        }
+    }
 }
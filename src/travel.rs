@@ -0,0 +1,340 @@
+use rltk::BaseMap;
+use specs::prelude::*;
+use specs_derive::Component;
+
+use crate::commands::{CommandArgs, CommandRegistry, TextCommand};
+use crate::components::{
+    ActiveDescriptionComponent, ColliderComponent, CombatStats, DebugHudComponent, Description, Movement, Player, PlayerInputComponent,
+    Position,
+};
+use crate::game::CurrentRoom;
+use crate::room::{ExitData, ExitDirection, ExitTrigger, RoomData, RoomMap, ROOM_HEIGHT, ROOM_WIDTH};
+use crate::textinput::TextParser;
+use crate::visibility::{can_player_see, Viewshed};
+
+// how long the player waits between steps, so travelling can be watched
+const STEP_DELAY_MS: f32 = 100.0;
+
+// walks the player out of one exit after another, stopping for anything new that shows up on the way
+#[derive(Component)]
+pub struct Travel {
+    route: Vec<ExitData>,      // the exits still to take, the first one is in the current room
+    room: i32,                 // the room the player was in at the last step
+    seen_enemies: Vec<Entity>, // enemies that were already in view, only new ones stop the travel
+    step_time_ms: f32,
+    destination: Option<String>,
+}
+
+fn visible_enemies<'a>(
+    entities: &Entities<'a>,
+    players: &ReadStorage<'a, Player>,
+    viewsheds: &ReadStorage<'a, Viewshed>,
+    positions: &ReadStorage<'a, Position>,
+    combat_stats: &ReadStorage<'a, CombatStats>,
+) -> Vec<Entity> {
+    (entities, positions, combat_stats)
+        .join()
+        .filter(|(entity, position, _combat_stats)| !players.contains(*entity) && can_player_see(players, viewsheds, position))
+        .map(|(entity, _position, _combat_stats)| entity)
+        .collect()
+}
+
+fn start_travel(world: &mut World, player: Entity, route: Vec<ExitData>, destination: Option<String>) {
+    let seen_enemies = visible_enemies(
+        &world.entities(),
+        &world.read_storage::<Player>(),
+        &world.read_storage::<Viewshed>(),
+        &world.read_storage::<Position>(),
+        &world.read_storage::<CombatStats>(),
+    );
+    let travel = Travel {
+        route: route,
+        room: world.fetch::<CurrentRoom>().get_room_index(),
+        seen_enemies: seen_enemies,
+        step_time_ms: STEP_DELAY_MS,
+        destination: destination,
+    };
+    world
+        .write_storage::<Travel>()
+        .insert(player, travel)
+        .expect("failed to start travelling");
+}
+
+// the first step towards the closest exit trigger going that way
+fn step_towards_exit<'a>(
+    room_map: &RoomMap,
+    position: &Position,
+    direction: ExitDirection,
+    exit_triggers: &ReadStorage<'a, ExitTrigger>,
+    positions: &ReadStorage<'a, Position>,
+) -> Option<Position> {
+    let mut best: Option<(usize, Position)> = None;
+    for (exit_trigger, exit_position) in (exit_triggers, positions).join() {
+        if exit_trigger.from_direction != direction {
+            continue;
+        }
+
+        // edge exits sit just outside the room, so head for the tile inside it first
+        let inside = Position {
+            x: i32::max(0, i32::min(ROOM_WIDTH - 1, exit_position.x)),
+            y: i32::max(0, i32::min(ROOM_HEIGHT - 1, exit_position.y)),
+        };
+        let (length, step) = if position == exit_position {
+            // already standing on the stairs, step off so they can be taken
+            let (neighbour_index, _cost) = *room_map.get_available_exits(room_map.index_of(position)?).first()?;
+            (2, room_map.position_of(neighbour_index))
+        } else if *position == inside {
+            (1, exit_position.clone())
+        } else {
+            match room_map.find_path(position, &inside) {
+                Some(path) if !path.is_empty() => (path.len() + if inside == *exit_position { 0 } else { 1 }, path[0].clone()),
+                _ => continue,
+            }
+        };
+
+        if best.as_ref().is_none_or(|(best_length, _)| length < *best_length) {
+            best = Some((length, step));
+        }
+    }
+    best.map(|(_length, step)| step)
+}
+
+pub struct TravelSystem {
+    frame_time_ms: f32,
+}
+
+impl TravelSystem {
+    pub fn new(frame_time_ms: f32) -> Self {
+        Self {
+            frame_time_ms: frame_time_ms,
+        }
+    }
+}
+
+impl<'a> System<'a> for TravelSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, CurrentRoom>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, ColliderComponent>,
+        ReadStorage<'a, Movement>,
+        ReadStorage<'a, ExitTrigger>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Description>,
+        ReadStorage<'a, Viewshed>,
+        WriteStorage<'a, Travel>,
+        WriteStorage<'a, PlayerInputComponent>,
+        WriteStorage<'a, ActiveDescriptionComponent>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            current_room,
+            players,
+            positions,
+            colliders,
+            movements,
+            exit_triggers,
+            combat_stats,
+            descriptions,
+            viewsheds,
+            mut travels,
+            mut player_inputs,
+            mut active_descriptions,
+        ): Self::SystemData,
+    ) {
+        let room = current_room.get_room_index();
+        let enemies = visible_enemies(&entities, &players, &viewsheds, &positions, &combat_stats);
+
+        let mut finished = Vec::new();
+        for (entity, position, movement, travel, player_input, active_description) in (
+            &entities,
+            &positions,
+            &movements,
+            &mut travels,
+            &mut player_inputs,
+            &mut active_descriptions,
+        )
+            .join()
+        {
+            // any movement key takes over from the travel
+            if player_input.move_left || player_input.move_right || player_input.move_up || player_input.move_down {
+                finished.push(entity);
+                continue;
+            }
+
+            if travel.room != room {
+                // ended up somewhere other than the next room, ie. through a secret passage
+                if travel.route.first().is_none_or(|exit_data| exit_data.to_room != room) {
+                    finished.push(entity);
+                    continue;
+                }
+
+                travel.route.remove(0);
+                travel.room = room;
+                travel.seen_enemies.clear();
+            }
+
+            if let Some(enemy) = enemies.iter().find(|enemy| !travel.seen_enemies.contains(enemy)) {
+                let enemy_name = descriptions
+                    .get(*enemy)
+                    .map(|description| description.name.to_ascii_lowercase())
+                    .unwrap_or_else(|| "enemy".to_string());
+                active_description.set(format!("you stop, you can see the {}", enemy_name).as_str());
+                finished.push(entity);
+                continue;
+            }
+
+            if travel.route.is_empty() {
+                if let Some(destination) = &travel.destination {
+                    active_description.set(format!("you arrive in the {}", destination).as_str());
+                }
+                finished.push(entity);
+                continue;
+            }
+
+            if movement.was_move_blocked() {
+                active_description.set("something is in the way");
+                finished.push(entity);
+                continue;
+            }
+
+            travel.step_time_ms += self.frame_time_ms;
+            if travel.step_time_ms < STEP_DELAY_MS {
+                continue;
+            }
+            travel.step_time_ms = 0.0;
+
+            // the step goes in as if it was typed, so the rest of the turn plays out the same as walking
+            let direction = travel.route[0].direction;
+            let mut room_map = RoomMap::from_colliders(&positions, &colliders, &movements);
+            for (exit_trigger, exit_position) in (&exit_triggers, &positions).join() {
+                if exit_trigger.from_direction != direction {
+                    room_map.block(exit_position);
+                }
+            }
+            match step_towards_exit(&room_map, position, direction, &exit_triggers, &positions) {
+                Some(step) => {
+                    let (delta_x, delta_y) = Position::delta(position, &step);
+                    player_input.move_left = delta_x < 0;
+                    player_input.move_right = delta_x > 0;
+                    player_input.move_up = delta_y < 0;
+                    player_input.move_down = delta_y > 0;
+                }
+                None => {
+                    active_description.set(format!("you can't find a way {}", direction.name()).as_str());
+                    finished.push(entity);
+                }
+            }
+        }
+
+        for entity in finished {
+            travels.remove(entity);
+        }
+    }
+}
+
+pub fn register_commands(registry: &mut CommandRegistry) {
+    registry.register(GoCommand {});
+    registry.register(TravelCommand {});
+}
+
+struct GoCommand {}
+
+impl TextCommand for GoCommand {
+    fn name(&self) -> &'static str {
+        "go"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["n", "s", "e", "w", "u", "d", "north", "south", "east", "west", "up", "down"]
+    }
+
+    fn help(&self) -> &'static str {
+        "go <direction> walks you to that exit and through it"
+    }
+
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String> {
+        let direction_name = match (args.verb.as_str(), &args.arg) {
+            ("go", Some(direction_name)) => direction_name.as_str(),
+            ("go", None) => return Some("go where?".to_string()),
+            (verb, _) => verb,
+        };
+
+        // the debug hud can still jump straight to a room by number
+        if let Ok(to_room) = direction_name.parse::<i32>() {
+            if world.read_storage::<DebugHudComponent>().contains(args.player) {
+                return Some(crate::debug::go_to_room(world, to_room));
+            }
+        }
+
+        let direction = match ExitDirection::from_name(direction_name) {
+            Some(direction) => direction,
+            None => return Some(format!("you can't go {}", direction_name)),
+        };
+
+        let room = world.fetch::<CurrentRoom>().get_room_index();
+        match crate::room::get_room_exits(world, room)
+            .into_iter()
+            .find(|exit_data| exit_data.direction == direction)
+        {
+            Some(exit_data) => {
+                start_travel(world, args.player, vec![exit_data], None);
+                Some(format!("you head {}", direction.name()))
+            }
+            None => Some(format!("you can't go {} from here", direction.name())),
+        }
+    }
+}
+
+struct TravelCommand {}
+
+impl TextCommand for TravelCommand {
+    fn name(&self) -> &'static str {
+        "travel"
+    }
+
+    fn help(&self) -> &'static str {
+        "travel <room> walks you through the castle to the nearest room with that name"
+    }
+
+    fn execute(&self, world: &mut World, args: &CommandArgs) -> Option<String> {
+        let room_name = match &args.arg {
+            Some(room_name) => room_name.to_ascii_lowercase(),
+            None => return Some("travel where?".to_string()),
+        };
+
+        let room_names: Vec<String> = world
+            .fetch::<Vec<RoomData>>()
+            .iter()
+            .map(|room_data| room_data.get_name())
+            .collect();
+        let (wanted, searchable_names) = {
+            let text_parser = world.fetch::<TextParser>();
+            let searchable_names: Vec<String> = room_names.iter().map(|name| text_parser.without_articles(name)).collect();
+            (text_parser.without_articles(room_name.as_str()), searchable_names)
+        };
+        let is_wanted = |room: i32| searchable_names[room as usize].contains(wanted.as_str());
+
+        if !(0..room_names.len() as i32).any(is_wanted) {
+            return Some(format!("you don't know anywhere called {}", room_name));
+        }
+
+        let room = world.fetch::<CurrentRoom>().get_room_index();
+        if is_wanted(room) {
+            return Some(format!("you are already in the {}", room_names[room as usize]));
+        }
+
+        match crate::room::find_route(world, room, is_wanted) {
+            Some((destination, route)) => {
+                let destination_name = room_names[destination as usize].clone();
+                start_travel(world, args.player, route, Some(destination_name.clone()));
+                Some(format!("you set off for the {}", destination_name))
+            }
+            None => Some(format!("you can't find a way to the {}", room_name)),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use rltk::Point;
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use specs_derive::Component;
+
+use crate::components::*;
+use crate::game::CurrentRoom;
+use crate::room::{RoomData, RoomMap, ROOM_HEIGHT, ROOM_WIDTH};
+
+// the player can see the whole room, enemies only notice what's fairly close
+pub const PLAYER_VIEW_RANGE: i32 = 24;
+pub const ENEMY_VIEW_RANGE: i32 = 8;
+
+// how far the player can see without a light, as a squared distance
+const DARK_VIEW_DISTANCE_SQ: i32 = 2;
+
+// what an entity can see in the current room, and (for the player) what it has seen before
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Viewshed {
+    pub range: i32,
+    room: i32, // remembered tiles are only good for the room they were seen in
+    visible: Vec<bool>,
+    remembered: Vec<bool>,
+}
+
+impl Viewshed {
+    pub fn new(range: i32) -> Self {
+        Self {
+            range: range,
+            room: -1,
+            visible: vec![false; (ROOM_WIDTH * ROOM_HEIGHT) as usize],
+            remembered: vec![false; (ROOM_WIDTH * ROOM_HEIGHT) as usize],
+        }
+    }
+
+    fn index_of(position: &Position) -> Option<usize> {
+        if position.x < 0 || position.x >= ROOM_WIDTH || position.y < 0 || position.y >= ROOM_HEIGHT {
+            return None;
+        }
+        Some((position.y * ROOM_WIDTH + position.x) as usize)
+    }
+
+    pub fn is_visible(&self, position: &Position) -> bool {
+        match Viewshed::index_of(position) {
+            Some(index) => self.visible[index],
+            None => false,
+        }
+    }
+
+    pub fn is_remembered(&self, position: &Position) -> bool {
+        match Viewshed::index_of(position) {
+            Some(index) => self.remembered[index],
+            None => false,
+        }
+    }
+
+    fn update(&mut self, room: i32, visible_positions: Vec<Position>) {
+        if self.room != room {
+            self.room = room;
+            self.remembered.iter_mut().for_each(|remembered| *remembered = false);
+        }
+
+        self.visible.iter_mut().for_each(|visible| *visible = false);
+        for position in visible_positions {
+            if let Some(index) = Viewshed::index_of(&position) {
+                self.visible[index] = true;
+                self.remembered[index] = true;
+            }
+        }
+    }
+}
+
+pub struct VisibilitySystem {}
+
+impl<'a> System<'a> for VisibilitySystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, CurrentRoom>,
+        ReadExpect<'a, Vec<RoomData>>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, InventoryComponent>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, ColliderComponent>,
+        ReadStorage<'a, Movement>,
+        WriteStorage<'a, Viewshed>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, current_room, room_datas, players, inventories, positions, colliders, movements, mut viewsheds): Self::SystemData,
+    ) {
+        let room = current_room.get_room_index();
+        let room_data = &room_datas[room as usize];
+        let room_map = RoomMap::from_colliders(&positions, &colliders, &movements);
+
+        for (entity, position, viewshed) in (&entities, &positions, &mut viewsheds).join() {
+            let center = Point::new(position.x, position.y);
+            let mut visible_positions: Vec<Position> = rltk::field_of_view(center, viewshed.range, &room_map)
+                .iter()
+                .map(|point| Position { x: point.x, y: point.y })
+                .collect();
+
+            // in the dark the player can only make out whatever is right next to them
+            if players.contains(entity) && room_data.is_too_dark(inventories.get(entity)) {
+                visible_positions.retain(|visible_position| Position::distance_sq(position, visible_position) <= DARK_VIEW_DISTANCE_SQ);
+            }
+
+            viewshed.update(room, visible_positions);
+        }
+    }
+}
+
+// whether any player can see the position, everything counts as visible when nobody has a viewshed
+pub fn can_player_see<'a>(players: &ReadStorage<'a, Player>, viewsheds: &ReadStorage<'a, Viewshed>, position: &Position) -> bool {
+    let mut has_viewshed = false;
+    for (_player, viewshed) in (players, viewsheds).join() {
+        if viewshed.is_visible(position) {
+            return true;
+        }
+        has_viewshed = true;
+    }
+    !has_viewshed
+}
+
+pub fn can_player_remember<'a>(players: &ReadStorage<'a, Player>, viewsheds: &ReadStorage<'a, Viewshed>, position: &Position) -> bool {
+    (players, viewsheds)
+        .join()
+        .any(|(_player, viewshed)| viewshed.is_remembered(position))
+}